@@ -0,0 +1,189 @@
+//! DB2 SQL communication area (SQLCA) of the last call on a statement or connection
+use super::{ffi, Return};
+use encoding_rs::Encoding;
+
+/// Separator of the message tokens in `sqlerrmc`
+const TOKEN_SEPARATOR: u8 = 0xFF;
+
+/// SQL communication area, as returned by `SQLGetSQLCA`
+///
+/// The SQLCA holds the DB2 specific details of the last SQL statement processed by the server,
+/// which are not part of the diagnostic records, e.g. the number of rows affected or the
+/// estimated cost of a prepared statement.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Sqlca {
+    /// SQLCODE, negative for errors and positive for warnings
+    pub sqlcode: i32,
+    /// Message tokens (`sqlerrmc`), which are inserted into the message text of the SQLCODE
+    pub tokens: Vec<String>,
+    /// Product signature (`sqlerrp`), e.g. `SQL11055` for DB2 11.5.5, or the name of the module
+    /// which reported an error
+    pub sqlerrp: String,
+    /// Diagnostic counters (`sqlerrd`), see `rows_affected` and `estimated_cost`
+    pub sqlerrd: [i32; 6],
+    /// Warning flags (`sqlwarn`). Blank if not set, `sqlwarn[0]` is `W` if any other flag is set.
+    pub sqlwarn: [char; 11],
+    /// SQLSTATE of the statement
+    pub sqlstate: String,
+}
+
+impl Sqlca {
+    fn from_raw(raw: &crate::sqlca, encoding: &'static Encoding) -> Sqlca {
+        let tokens_length = (raw.sqlerrml.max(0) as usize).min(raw.sqlerrmc.len());
+        let tokens = if tokens_length == 0 {
+            Vec::new()
+        } else {
+            bytes(&raw.sqlerrmc[..tokens_length])
+                .split(|&b| b == TOKEN_SEPARATOR)
+                .map(|token| decode(token, encoding))
+                .collect()
+        };
+        let mut sqlwarn = [' '; 11];
+        for (flag, &raw_flag) in sqlwarn.iter_mut().zip(raw.sqlwarn.iter()) {
+            if raw_flag != 0 {
+                *flag = raw_flag as u8 as char;
+            }
+        }
+        Sqlca {
+            sqlcode: raw.sqlcode,
+            tokens,
+            sqlerrp: decode(bytes(&raw.sqlerrp), encoding)
+                .trim_end()
+                .to_string(),
+            sqlerrd: raw.sqlerrd,
+            sqlwarn,
+            sqlstate: decode(bytes(&raw.sqlstate), encoding),
+        }
+    }
+
+    /// Number of rows affected by an `INSERT`, `UPDATE`, `DELETE` or `MERGE` (`sqlerrd[2]`). After
+    /// a `PREPARE` this is the estimated number of rows returned.
+    pub fn rows_affected(&self) -> i32 {
+        self.sqlerrd[2]
+    }
+
+    /// Relative cost estimated by the optimizer after a `PREPARE`, in timerons (`sqlerrd[3]`)
+    pub fn estimated_cost(&self) -> i32 {
+        self.sqlerrd[3]
+    }
+
+    /// `true` if any warning flag is set
+    pub fn has_warnings(&self) -> bool {
+        matches!(self.sqlwarn[0], 'W' | 'w')
+    }
+}
+
+fn bytes(chars: &[std::os::raw::c_char]) -> &[u8] {
+    unsafe { &*(chars as *const [std::os::raw::c_char] as *const [u8]) }
+}
+
+fn decode(bytes: &[u8], encoding: &'static Encoding) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    encoding.decode(&bytes[..end]).0.into_owned()
+}
+
+/// Retrieves the SQLCA of the last call on a connection or, if `statement` is not null, a
+/// statement. The message tokens are decoded with the OS encoding `encoding`.
+pub(crate) fn get_sqlca(
+    connection: ffi::SQLHDBC,
+    statement: ffi::SQLHSTMT,
+    encoding: &'static Encoding,
+) -> Return<Sqlca> {
+    let mut raw = crate::sqlca {
+        sqlcaid: [0; 8],
+        sqlcabc: 0,
+        sqlcode: 0,
+        sqlerrml: 0,
+        sqlerrmc: [0; 70],
+        sqlerrp: [0; 8],
+        sqlerrd: [0; 6],
+        sqlwarn: [0; 11],
+        sqlstate: [0; 5],
+    };
+    let ret = unsafe {
+        crate::SQLGetSQLCA(
+            0,
+            ffi::cli_handle(connection),
+            ffi::cli_handle(statement),
+            &mut raw,
+        )
+    };
+    Return::from_cli("SQLGetSQLCA", ret, Sqlca::from_raw(&raw, encoding))
+}
+
+/// SQLCA of the last call, if it has been processed by the server and reported a non-zero SQLCODE
+pub(crate) fn last_sqlca(
+    connection: ffi::SQLHDBC,
+    statement: ffi::SQLHSTMT,
+    encoding: &'static Encoding,
+) -> Option<Sqlca> {
+    match get_sqlca(connection, statement, encoding) {
+        Return::Success(sqlca) | Return::SuccessWithInfo(sqlca) if sqlca.sqlcode != 0 => {
+            Some(sqlca)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_raw() {
+        let mut raw = crate::sqlca {
+            sqlcaid: [0; 8],
+            sqlcabc: 136,
+            sqlcode: -803,
+            sqlerrml: 0,
+            sqlerrmc: [0; 70],
+            sqlerrp: [0; 8],
+            sqlerrd: [0, 0, 0, 0, 0, 0],
+            sqlwarn: [0; 11],
+            sqlstate: [0; 5],
+        };
+        let tokens = b"1\xFFDB2INST1.EMPLOYEE";
+        for (dst, &src) in raw.sqlerrmc.iter_mut().zip(tokens.iter()) {
+            *dst = src as std::os::raw::c_char;
+        }
+        raw.sqlerrml = tokens.len() as std::os::raw::c_short;
+        for (dst, &src) in raw.sqlerrp.iter_mut().zip(b"SQLRI01F".iter()) {
+            *dst = src as std::os::raw::c_char;
+        }
+        for (dst, &src) in raw.sqlstate.iter_mut().zip(b"23505".iter()) {
+            *dst = src as std::os::raw::c_char;
+        }
+        raw.sqlerrd[2] = 7;
+        raw.sqlwarn[0] = b'W' as std::os::raw::c_char;
+        raw.sqlwarn[1] = b'W' as std::os::raw::c_char;
+
+        let sqlca = Sqlca::from_raw(&raw, encoding_rs::UTF_8);
+        assert_eq!(sqlca.sqlcode, -803);
+        assert_eq!(sqlca.tokens, vec!["1", "DB2INST1.EMPLOYEE"]);
+        assert_eq!(sqlca.sqlerrp, "SQLRI01F");
+        assert_eq!(sqlca.sqlstate, "23505");
+        assert_eq!(sqlca.rows_affected(), 7);
+        assert!(sqlca.has_warnings());
+        assert_eq!(sqlca.sqlwarn[1], 'W');
+        assert_eq!(sqlca.sqlwarn[2], ' ');
+    }
+
+    #[test]
+    fn from_raw_without_tokens() {
+        let raw = crate::sqlca {
+            sqlcaid: [0; 8],
+            sqlcabc: 136,
+            sqlcode: 0,
+            sqlerrml: 0,
+            sqlerrmc: [0; 70],
+            sqlerrp: [0; 8],
+            sqlerrd: [0; 6],
+            sqlwarn: [0; 11],
+            sqlstate: [0; 5],
+        };
+        let sqlca = Sqlca::from_raw(&raw, encoding_rs::UTF_8);
+        assert!(sqlca.tokens.is_empty());
+        assert!(!sqlca.has_warnings());
+        assert_eq!(sqlca.sqlstate, "");
+    }
+}
@@ -0,0 +1,125 @@
+//! Raw access to connection attributes via `SQLSetConnectAttr` and `SQLGetConnectAttr`, or their
+//! wide variants if the connection uses the wide functions
+use super::super::{ffi, Handle, Result, Return};
+use super::Connection;
+use odbc_safe::AutocommitMode;
+use std::mem::size_of;
+
+impl<'env, AC: AutocommitMode> Connection<'env, AC> {
+    /// Sets an integer valued connection attribute
+    pub(crate) fn set_attr_u32(&mut self, attribute: u32, value: u32) -> Result<()> {
+        self.set_attr(
+            attribute,
+            value as usize as ffi::SQLPOINTER,
+            crate::SQL_IS_UINTEGER,
+        )
+        .into_result(&*self)
+    }
+
+    /// Sets a character string valued connection attribute
+    pub(crate) fn set_attr_string(&mut self, attribute: u32, value: &str) -> Result<()> {
+        let bytes = self.encoding.encode_text(value);
+        self.set_attr(
+            attribute,
+            bytes.as_ptr() as *mut u8 as ffi::SQLPOINTER,
+            bytes.len() as ffi::SQLINTEGER,
+        )
+        .into_result(&*self)
+    }
+
+    /// Reads an integer valued connection attribute
+    pub(crate) fn get_attr_u32(&self, attribute: u32) -> Result<u32> {
+        let mut value: u32 = 0;
+        self.get_attr(
+            attribute,
+            &mut value as *mut u32 as ffi::SQLPOINTER,
+            size_of::<u32>(),
+        )
+        .into_result(self)?;
+        Ok(value)
+    }
+
+    /// Reads a character string valued connection attribute
+    pub(crate) fn get_attr_string(&self, attribute: u32) -> Result<String> {
+        let null_bytes = if self.encoding.is_wide() { 2 } else { 1 };
+        let mut buffer = vec![0u8; 256];
+        loop {
+            let length = self
+                .get_attr(
+                    attribute,
+                    buffer.as_mut_ptr() as ffi::SQLPOINTER,
+                    buffer.len(),
+                )
+                .into_result(self)?;
+            let length = if length < 0 { 0 } else { length as usize };
+            // The value has been truncated if it does not fit into the buffer together with its
+            // terminating zero.
+            if length < buffer.len() {
+                buffer.truncate(length);
+                break;
+            }
+            buffer.resize(length + null_bytes, 0);
+        }
+        let mut value = self.encoding.decode_text(&buffer).into_owned();
+        // Some attributes are reported with their terminating zero(s) included in the length
+        while value.ends_with('\0') {
+            value.pop();
+        }
+        Ok(value)
+    }
+
+    fn set_attr(
+        &self,
+        attribute: u32,
+        value: ffi::SQLPOINTER,
+        string_length: ffi::SQLINTEGER,
+    ) -> Return<()> {
+        let ret = unsafe {
+            if self.encoding.is_wide() {
+                crate::SQLSetConnectAttrW(
+                    ffi::cli_handle(self.handle()),
+                    attribute as crate::SQLINTEGER,
+                    value,
+                    string_length,
+                )
+            } else {
+                crate::SQLSetConnectAttr(
+                    ffi::cli_handle(self.handle()),
+                    attribute as crate::SQLINTEGER,
+                    value,
+                    string_length,
+                )
+            }
+        };
+        Return::from_cli("SQLSetConnectAttr", ret, ())
+    }
+
+    fn get_attr(
+        &self,
+        attribute: u32,
+        value: ffi::SQLPOINTER,
+        buffer_length: usize,
+    ) -> Return<ffi::SQLINTEGER> {
+        let mut string_length: ffi::SQLINTEGER = 0;
+        let ret = unsafe {
+            if self.encoding.is_wide() {
+                crate::SQLGetConnectAttrW(
+                    ffi::cli_handle(self.handle()),
+                    attribute as crate::SQLINTEGER,
+                    value,
+                    buffer_length as ffi::SQLINTEGER,
+                    &mut string_length as *mut ffi::SQLINTEGER,
+                )
+            } else {
+                crate::SQLGetConnectAttr(
+                    ffi::cli_handle(self.handle()),
+                    attribute as crate::SQLINTEGER,
+                    value,
+                    buffer_length as ffi::SQLINTEGER,
+                    &mut string_length as *mut ffi::SQLINTEGER,
+                )
+            }
+        };
+        Return::from_cli("SQLGetConnectAttr", ret, string_length)
+    }
+}
@@ -0,0 +1,93 @@
+//! Client information used by DB2 to attribute workload to applications
+use super::super::Result;
+use super::Connection;
+use odbc_safe::AutocommitMode;
+
+/// Client information reported to the DB2 server. It shows up in monitoring functions like
+/// `MON_GET_CONNECTION` and can be used by workload management to classify connections.
+///
+/// Fields which are `None` are left unchanged when the information is applied.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClientInfo {
+    /// Client user ID (`SQL_ATTR_INFO_USERID`), at most 255 characters
+    pub user_id: Option<String>,
+    /// Client workstation name (`SQL_ATTR_INFO_WRKSTNNAME`), at most 255 characters
+    pub workstation_name: Option<String>,
+    /// Client application name (`SQL_ATTR_INFO_APPLNAME`), at most 255 characters
+    pub application_name: Option<String>,
+    /// Client accounting string (`SQL_ATTR_INFO_ACCTSTR`), at most 255 characters
+    pub accounting_string: Option<String>,
+}
+
+impl ClientInfo {
+    /// Creates empty client information, leaving every attribute unchanged
+    pub fn new() -> ClientInfo {
+        ClientInfo::default()
+    }
+
+    pub fn user_id<S: Into<String>>(mut self, user_id: S) -> ClientInfo {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    pub fn workstation_name<S: Into<String>>(mut self, workstation_name: S) -> ClientInfo {
+        self.workstation_name = Some(workstation_name.into());
+        self
+    }
+
+    pub fn application_name<S: Into<String>>(mut self, application_name: S) -> ClientInfo {
+        self.application_name = Some(application_name.into());
+        self
+    }
+
+    pub fn accounting_string<S: Into<String>>(mut self, accounting_string: S) -> ClientInfo {
+        self.accounting_string = Some(accounting_string.into());
+        self
+    }
+
+    fn attributes(&self) -> [(u32, &Option<String>); 4] {
+        [
+            (crate::SQL_ATTR_INFO_USERID, &self.user_id),
+            (crate::SQL_ATTR_INFO_WRKSTNNAME, &self.workstation_name),
+            (crate::SQL_ATTR_INFO_APPLNAME, &self.application_name),
+            (crate::SQL_ATTR_INFO_ACCTSTR, &self.accounting_string),
+        ]
+    }
+}
+
+impl<'env, AC: AutocommitMode> Connection<'env, AC> {
+    /// Sends the given client information to the server. Fields which are `None` keep their
+    /// current value. The information is transmitted with the next request to the server.
+    pub fn set_client_info(&mut self, info: &ClientInfo) -> Result<()> {
+        for (attribute, value) in info.attributes().iter() {
+            if let Some(value) = value {
+                self.set_attr_string(*attribute, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The client information currently set on this connection
+    pub fn client_info(&self) -> Result<ClientInfo> {
+        Ok(ClientInfo {
+            user_id: Some(self.get_attr_string(crate::SQL_ATTR_INFO_USERID)?),
+            workstation_name: Some(self.get_attr_string(crate::SQL_ATTR_INFO_WRKSTNNAME)?),
+            application_name: Some(self.get_attr_string(crate::SQL_ATTR_INFO_APPLNAME)?),
+            accounting_string: Some(self.get_attr_string(crate::SQL_ATTR_INFO_ACCTSTR)?),
+        })
+    }
+
+    /// The application ID assigned to this connection by DB2
+    /// (`SQL_ATTR_DB2_APPLICATION_ID`), e.g. `*LOCAL.db2inst1.200101120000`. Matches the
+    /// `APPLICATION_ID` column of `MON_GET_CONNECTION`.
+    pub fn application_id(&self) -> Result<String> {
+        self.get_attr_string(crate::SQL_ATTR_DB2_APPLICATION_ID)
+    }
+
+    /// The application handle assigned to this connection by DB2
+    /// (`SQL_ATTR_DB2_APPLICATION_HANDLE`). Matches the `APPLICATION_HANDLE` column of
+    /// `MON_GET_CONNECTION`.
+    pub fn application_handle(&self) -> Result<u32> {
+        self.get_attr_u32(crate::SQL_ATTR_DB2_APPLICATION_HANDLE)
+    }
+}
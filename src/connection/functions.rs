@@ -0,0 +1,98 @@
+//! Probing of the CLI functions supported by a data source via `SQLGetFunctions`
+use super::super::{ffi, Error, Handle, Result, Return};
+use super::Connection;
+use odbc_safe::AutocommitMode;
+use std::fmt;
+
+/// Set of the CLI functions supported by a data source, as reported by `SQLGetFunctions`
+///
+/// DB2 for Linux, UNIX and Windows, DB2 for z/OS and DB2 for i do not support the same set of
+/// functions. Check for a function before relying on it, rather than interpreting a driver
+/// specific `HYC00` error.
+///
+/// Can be obtained via `Connection::supported_functions`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SupportedFunctions(Vec<u16>);
+
+impl SupportedFunctions {
+    /// `true` if the function identified by `function_id` is supported. `function_id` is one of
+    /// the `SQL_API_*` constants, e.g. `SQL_API_SQLTABLEPRIVILEGES`.
+    pub fn is_supported(&self, function_id: u32) -> bool {
+        let index = (function_id >> 4) as usize;
+        match self.0.get(index) {
+            Some(bits) => bits & (1 << (function_id & 0xF)) != 0,
+            None => false,
+        }
+    }
+}
+
+/// Returned by higher level APIs if the data source does not support a CLI function they depend
+/// on.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NotSupported {
+    /// Name of the unsupported CLI function, e.g. `SQLTablePrivileges`
+    pub function: &'static str,
+}
+
+impl fmt::Display for NotSupported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is not supported by this data source", self.function)
+    }
+}
+
+impl std::error::Error for NotSupported {}
+
+impl<'env, AC: AutocommitMode> Connection<'env, AC> {
+    /// The CLI functions supported by the data source. Queried once per connection.
+    pub fn supported_functions(&self) -> Result<SupportedFunctions> {
+        if let Some(functions) = &self.info.borrow().functions {
+            return Ok(functions.clone());
+        }
+        let functions = self.get_functions().into_result(self)?;
+        self.info.borrow_mut().functions = Some(functions.clone());
+        Ok(functions)
+    }
+
+    /// Fails with `NotSupported` if the data source does not support the function identified by
+    /// `function_id`.
+    pub(crate) fn require_function(
+        &self,
+        function_id: u32,
+        function: &'static str,
+    ) -> Result<()> {
+        if self.supported_functions()?.is_supported(function_id) {
+            Ok(())
+        } else {
+            Err(Error::NotSupported(NotSupported { function }))
+        }
+    }
+
+    fn get_functions(&self) -> Return<SupportedFunctions> {
+        let mut bits = vec![0u16; crate::SQL_API_ODBC3_ALL_FUNCTIONS_SIZE as usize];
+        let ret = unsafe {
+            crate::SQLGetFunctions(
+                ffi::cli_handle(self.handle()),
+                crate::SQL_API_ODBC3_ALL_FUNCTIONS as crate::SQLUSMALLINT,
+                bits.as_mut_ptr(),
+            )
+        };
+        Return::from_cli("SQLGetFunctions", ret, SupportedFunctions(bits))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn is_supported() {
+        let mut bits = vec![0u16; crate::SQL_API_ODBC3_ALL_FUNCTIONS_SIZE as usize];
+        // SQL_API_SQLTABLEPRIVILEGES = 70 is bit 6 of the fifth element
+        bits[4] = 1 << 6;
+        let functions = SupportedFunctions(bits);
+        assert!(functions.is_supported(crate::SQL_API_SQLTABLEPRIVILEGES));
+        assert!(!functions.is_supported(crate::SQL_API_SQLCOLUMNPRIVILEGES));
+        assert!(!functions.is_supported(u32::from(u16::MAX)));
+    }
+}
@@ -0,0 +1,340 @@
+//! Typed access to `SQLGetInfo`, or `SQLGetInfoW` if the connection uses the wide functions
+use super::super::{ffi, Handle, Result, Return};
+use super::{Connection, IsolationLevel, SupportedFunctions};
+use odbc_safe::AutocommitMode;
+use std::collections::HashMap;
+use std::mem::size_of;
+
+/// Values returned by `SQLGetInfo` and `SQLGetFunctions` do not change during the lifetime of a
+/// connection, so each info type is only queried once per connection.
+#[derive(Debug, Default)]
+pub(crate) struct InfoCache {
+    strings: HashMap<u16, String>,
+    numbers: HashMap<u16, u32>,
+    pub(super) functions: Option<SupportedFunctions>,
+}
+
+/// Maximum lengths of identifiers supported by the data source. `0` means there is no maximum or
+/// the maximum is unknown.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct IdentifierLengths {
+    pub column: u16,
+    pub cursor: u16,
+    pub schema: u16,
+    pub catalog: u16,
+    pub table: u16,
+    pub user: u16,
+}
+
+/// Set of transaction isolation levels, as reported by `SQL_TXN_ISOLATION_OPTION`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct IsolationLevels(u32);
+
+impl IsolationLevels {
+    /// Raw `SQL_TXN_*` bitmask
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Uncommitted Read (UR)
+    pub fn read_uncommitted(self) -> bool {
+        self.0 & crate::SQL_TXN_READ_UNCOMMITTED != 0
+    }
+
+    /// Cursor Stability (CS)
+    pub fn read_committed(self) -> bool {
+        self.0 & crate::SQL_TXN_READ_COMMITTED != 0
+    }
+
+    /// Read Stability (RS)
+    pub fn repeatable_read(self) -> bool {
+        self.0 & crate::SQL_TXN_REPEATABLE_READ != 0
+    }
+
+    /// Repeatable Read (RR)
+    pub fn serializable(self) -> bool {
+        self.0 & crate::SQL_TXN_SERIALIZABLE != 0
+    }
+
+    /// No Commit (NC), only available on DB2 for i
+    pub fn no_commit(self) -> bool {
+        self.0 & crate::SQL_TXN_NOCOMMIT != 0
+    }
+
+    /// `true` if `level` can be passed to `Connection::set_isolation_level`
+    pub fn contains(self, level: IsolationLevel) -> bool {
+        self.0 & level.to_raw() != 0
+    }
+}
+
+/// Code pages (CCSIDs) of a connection, as reported by `SQLGetInfo`
+///
+/// The client converts character data between the database and the application code page. See
+/// `encoding_for_ccsid` for the matching encodings.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Codepages {
+    /// Code page of the application, character data is exchanged with the client in it
+    pub application: u32,
+    /// Code page the database has been created with
+    pub database: u32,
+    /// Code page the client uses to communicate with the database
+    pub connection: u32,
+}
+
+/// What happens to open cursors and prepared statements on `COMMIT` or `ROLLBACK`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CursorBehavior {
+    /// Cursors are closed and prepared statements are deleted
+    Delete,
+    /// Cursors are closed, prepared statements can be executed again
+    Close,
+    /// Cursors and prepared statements are preserved
+    Preserve,
+}
+
+impl CursorBehavior {
+    fn from_raw(value: u32) -> Option<CursorBehavior> {
+        match value {
+            crate::SQL_CB_DELETE => Some(CursorBehavior::Delete),
+            crate::SQL_CB_CLOSE => Some(CursorBehavior::Close),
+            crate::SQL_CB_PRESERVE => Some(CursorBehavior::Preserve),
+            _ => None,
+        }
+    }
+}
+
+/// Cursor types and cursor attributes supported by the data source
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CursorCapabilities {
+    /// `SQL_SO_*` bitmask of the supported scroll options
+    pub scroll_options: u32,
+    /// `SQL_CA1_*` bitmask for forward only cursors
+    pub forward_only_attributes: u32,
+    /// `SQL_CA1_*` bitmask for static cursors
+    pub static_attributes: u32,
+    /// `SQL_CA1_*` bitmask for keyset driven cursors
+    pub keyset_attributes: u32,
+    /// `SQL_CA1_*` bitmask for dynamic cursors
+    pub dynamic_attributes: u32,
+    /// Behavior of cursors on `COMMIT`, `None` if the driver reports an unknown value
+    pub commit_behavior: Option<CursorBehavior>,
+    /// Behavior of cursors on `ROLLBACK`, `None` if the driver reports an unknown value
+    pub rollback_behavior: Option<CursorBehavior>,
+}
+
+impl CursorCapabilities {
+    /// `true` if static (insensitive scrollable) cursors are supported
+    pub fn supports_static(&self) -> bool {
+        self.scroll_options & crate::SQL_SO_STATIC != 0
+    }
+
+    /// `true` if keyset driven (sensitive scrollable) cursors are supported
+    pub fn supports_keyset_driven(&self) -> bool {
+        self.scroll_options & crate::SQL_SO_KEYSET_DRIVEN != 0
+    }
+
+    /// `true` if dynamic cursors are supported
+    pub fn supports_dynamic(&self) -> bool {
+        self.scroll_options & crate::SQL_SO_DYNAMIC != 0
+    }
+}
+
+impl<'env, AC: AutocommitMode> Connection<'env, AC> {
+    /// Name of the DBMS product, e.g. `DB2/LINUXX8664`, `DB2` (z/OS) or `AS` (IBM i)
+    pub fn dbms_name(&self) -> Result<String> {
+        self.info_string(crate::SQL_DBMS_NAME)
+    }
+
+    /// Version of the DBMS product in the form `##.##.####`
+    pub fn dbms_version(&self) -> Result<String> {
+        self.info_string(crate::SQL_DBMS_VER)
+    }
+
+    /// File name of the CLI driver library
+    pub fn driver_name(&self) -> Result<String> {
+        self.info_string(crate::SQL_DRIVER_NAME)
+    }
+
+    /// Version of the CLI driver in the form `##.##.####`
+    pub fn driver_version(&self) -> Result<String> {
+        self.info_string(crate::SQL_DRIVER_VER)
+    }
+
+    /// Name of the server (instance) the data source is located on
+    pub fn server_name(&self) -> Result<String> {
+        self.info_string(crate::SQL_SERVER_NAME)
+    }
+
+    /// Name of the current database
+    pub fn database_name(&self) -> Result<String> {
+        self.info_string(crate::SQL_DATABASE_NAME)
+    }
+
+    /// Authorization name used to connect to the data source
+    pub fn user_name(&self) -> Result<String> {
+        self.info_string(crate::SQL_USER_NAME)
+    }
+
+    /// Character used to delimit identifiers, a single space if delimited identifiers are not
+    /// supported
+    pub fn identifier_quote_char(&self) -> Result<String> {
+        self.info_string(crate::SQL_IDENTIFIER_QUOTE_CHAR)
+    }
+
+    /// Maximum lengths of the different kinds of identifiers
+    pub fn max_identifier_lengths(&self) -> Result<IdentifierLengths> {
+        Ok(IdentifierLengths {
+            column: self.info_u16(crate::SQL_MAX_COLUMN_NAME_LEN)?,
+            cursor: self.info_u16(crate::SQL_MAX_CURSOR_NAME_LEN)?,
+            schema: self.info_u16(crate::SQL_MAX_SCHEMA_NAME_LEN)?,
+            catalog: self.info_u16(crate::SQL_MAX_CATALOG_NAME_LEN)?,
+            table: self.info_u16(crate::SQL_MAX_TABLE_NAME_LEN)?,
+            user: self.info_u16(crate::SQL_MAX_USER_NAME_LEN)?,
+        })
+    }
+
+    /// Transaction isolation levels supported by the data source
+    pub fn supported_isolation_levels(&self) -> Result<IsolationLevels> {
+        self.info_u32(crate::SQL_TXN_ISOLATION_OPTION)
+            .map(IsolationLevels)
+    }
+
+    /// Supported cursor types and their attributes
+    pub fn cursor_capabilities(&self) -> Result<CursorCapabilities> {
+        Ok(CursorCapabilities {
+            scroll_options: self.info_u32(crate::SQL_SCROLL_OPTIONS)?,
+            forward_only_attributes: self.info_u32(crate::SQL_FORWARD_ONLY_CURSOR_ATTRIBUTES1)?,
+            static_attributes: self.info_u32(crate::SQL_STATIC_CURSOR_ATTRIBUTES1)?,
+            keyset_attributes: self.info_u32(crate::SQL_KEYSET_CURSOR_ATTRIBUTES1)?,
+            dynamic_attributes: self.info_u32(crate::SQL_DYNAMIC_CURSOR_ATTRIBUTES1)?,
+            commit_behavior: CursorBehavior::from_raw(
+                self.info_u16(crate::SQL_CURSOR_COMMIT_BEHAVIOR)? as u32,
+            ),
+            rollback_behavior: CursorBehavior::from_raw(
+                self.info_u16(crate::SQL_CURSOR_ROLLBACK_BEHAVIOR)? as u32,
+            ),
+        })
+    }
+
+    /// Code pages of the application, the database and the connection
+    pub fn codepages(&self) -> Result<Codepages> {
+        Ok(Codepages {
+            application: self.info_u32(crate::SQL_APPLICATION_CODEPAGE)?,
+            database: self.info_u32(crate::SQL_DATABASE_CODEPAGE)?,
+            connection: self.info_u32(crate::SQL_CONNECT_CODEPAGE)?,
+        })
+    }
+
+    /// Data source specific keywords, which are not part of the ODBC list of reserved words
+    pub fn keywords(&self) -> Result<Vec<String>> {
+        Ok(self
+            .info_string(crate::SQL_KEYWORDS)?
+            .split(',')
+            .map(str::trim)
+            .filter(|keyword| !keyword.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    /// Cached character string info value
+    pub(crate) fn info_string(&self, info_type: u32) -> Result<String> {
+        let info_type = info_type as u16;
+        if let Some(value) = self.info.borrow().strings.get(&info_type) {
+            return Ok(value.clone());
+        }
+        let value = self.query_info_string(info_type)?;
+        self.info
+            .borrow_mut()
+            .strings
+            .insert(info_type, value.clone());
+        Ok(value)
+    }
+
+    /// Cached `SQLUSMALLINT` info value
+    pub(crate) fn info_u16(&self, info_type: u32) -> Result<u16> {
+        let info_type = info_type as u16;
+        if let Some(value) = self.info.borrow().numbers.get(&info_type) {
+            return Ok(*value as u16);
+        }
+        let mut value: u16 = 0;
+        self.get_info(
+            info_type,
+            &mut value as *mut u16 as ffi::SQLPOINTER,
+            size_of::<u16>(),
+        )
+        .into_result(self)?;
+        self.info
+            .borrow_mut()
+            .numbers
+            .insert(info_type, u32::from(value));
+        Ok(value)
+    }
+
+    /// Cached `SQLUINTEGER` info value
+    pub(crate) fn info_u32(&self, info_type: u32) -> Result<u32> {
+        let info_type = info_type as u16;
+        if let Some(value) = self.info.borrow().numbers.get(&info_type) {
+            return Ok(*value);
+        }
+        let mut value: u32 = 0;
+        self.get_info(
+            info_type,
+            &mut value as *mut u32 as ffi::SQLPOINTER,
+            size_of::<u32>(),
+        )
+        .into_result(self)?;
+        self.info.borrow_mut().numbers.insert(info_type, value);
+        Ok(value)
+    }
+
+    fn query_info_string(&self, info_type: u16) -> Result<String> {
+        let null_bytes = if self.encoding.is_wide() { 2 } else { 1 };
+        let mut buffer = vec![0u8; 256];
+        loop {
+            let length = self
+                .get_info(
+                    info_type,
+                    buffer.as_mut_ptr() as ffi::SQLPOINTER,
+                    buffer.len(),
+                )
+                .into_result(self)? as usize;
+            // The value has been truncated if it does not fit into the buffer together with its
+            // terminating zero.
+            if length < buffer.len() {
+                buffer.truncate(length);
+                break;
+            }
+            buffer.resize(length + null_bytes, 0);
+        }
+        Ok(self.encoding.decode_text(&buffer).into_owned())
+    }
+
+    fn get_info(
+        &self,
+        info_type: u16,
+        value: ffi::SQLPOINTER,
+        buffer_length: usize,
+    ) -> Return<ffi::SQLSMALLINT> {
+        let mut string_length: ffi::SQLSMALLINT = 0;
+        let ret = unsafe {
+            if self.encoding.is_wide() {
+                crate::SQLGetInfoW(
+                    ffi::cli_handle(self.handle()),
+                    info_type,
+                    value,
+                    buffer_length as ffi::SQLSMALLINT,
+                    &mut string_length as *mut ffi::SQLSMALLINT,
+                )
+            } else {
+                crate::SQLGetInfo(
+                    ffi::cli_handle(self.handle()),
+                    info_type,
+                    value,
+                    buffer_length as ffi::SQLSMALLINT,
+                    &mut string_length as *mut ffi::SQLSMALLINT,
+                )
+            }
+        };
+        Return::from_cli("SQLGetInfo", ret, string_length)
+    }
+}
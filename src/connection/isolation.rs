@@ -0,0 +1,258 @@
+//! Transaction isolation, access mode and locking behavior of a connection
+use super::super::{Result, Statement};
+use super::Connection;
+use odbc_safe::{AutocommitMode, AutocommitOff};
+use std::ops::{Deref, DerefMut};
+
+/// Transaction isolation level. The variants are named after the DB2 isolation levels, the
+/// corresponding ODBC names are given in the documentation of each variant.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IsolationLevel {
+    /// Uncommitted Read (UR), `SQL_TXN_READ_UNCOMMITTED`
+    UncommittedRead,
+    /// Cursor Stability (CS), `SQL_TXN_READ_COMMITTED`
+    CursorStability,
+    /// Read Stability (RS), `SQL_TXN_REPEATABLE_READ`
+    ReadStability,
+    /// Repeatable Read (RR), `SQL_TXN_SERIALIZABLE`
+    RepeatableRead,
+    /// No Commit (NC), `SQL_TXN_NOCOMMIT`. Only supported by DB2 for i.
+    NoCommit,
+}
+
+impl IsolationLevel {
+    pub(crate) fn to_raw(self) -> u32 {
+        match self {
+            IsolationLevel::UncommittedRead => crate::SQL_TXN_READ_UNCOMMITTED,
+            IsolationLevel::CursorStability => crate::SQL_TXN_READ_COMMITTED,
+            IsolationLevel::ReadStability => crate::SQL_TXN_REPEATABLE_READ,
+            IsolationLevel::RepeatableRead => crate::SQL_TXN_SERIALIZABLE,
+            IsolationLevel::NoCommit => crate::SQL_TXN_NOCOMMIT,
+        }
+    }
+
+    pub(crate) fn from_raw(value: u32) -> Option<IsolationLevel> {
+        match value {
+            crate::SQL_TXN_READ_UNCOMMITTED => Some(IsolationLevel::UncommittedRead),
+            crate::SQL_TXN_READ_COMMITTED => Some(IsolationLevel::CursorStability),
+            crate::SQL_TXN_REPEATABLE_READ => Some(IsolationLevel::ReadStability),
+            crate::SQL_TXN_SERIALIZABLE => Some(IsolationLevel::RepeatableRead),
+            crate::SQL_TXN_NOCOMMIT => Some(IsolationLevel::NoCommit),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the connection may modify data. This is a hint to the driver, DB2 does not reject
+/// updates on a read only connection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AccessMode {
+    ReadWrite,
+    ReadOnly,
+}
+
+/// How DB2 resolves access to rows which are locked by other transactions, as set by
+/// `SQL_ATTR_CONCURRENT_ACCESS_RESOLUTION`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConcurrentAccessResolution {
+    /// Use the setting of the database or package
+    Unset,
+    /// Read the currently committed version of locked rows (Cursor Stability only)
+    UseCurrentlyCommitted,
+    /// Wait for the outcome of the transaction holding the lock
+    WaitForOutcome,
+    /// Skip rows which are locked by other transactions
+    SkipLockedData,
+}
+
+impl ConcurrentAccessResolution {
+    fn to_raw(self) -> u32 {
+        match self {
+            ConcurrentAccessResolution::Unset => crate::SQL_CONCURRENT_ACCESS_RESOLUTION_UNSET,
+            ConcurrentAccessResolution::UseCurrentlyCommitted => {
+                crate::SQL_USE_CURRENTLY_COMMITTED
+            }
+            ConcurrentAccessResolution::WaitForOutcome => crate::SQL_WAIT_FOR_OUTCOME,
+            ConcurrentAccessResolution::SkipLockedData => crate::SQL_SKIP_LOCKED_DATA,
+        }
+    }
+
+    fn from_raw(value: u32) -> Option<ConcurrentAccessResolution> {
+        match value {
+            crate::SQL_CONCURRENT_ACCESS_RESOLUTION_UNSET => {
+                Some(ConcurrentAccessResolution::Unset)
+            }
+            crate::SQL_USE_CURRENTLY_COMMITTED => {
+                Some(ConcurrentAccessResolution::UseCurrentlyCommitted)
+            }
+            crate::SQL_WAIT_FOR_OUTCOME => Some(ConcurrentAccessResolution::WaitForOutcome),
+            crate::SQL_SKIP_LOCKED_DATA => Some(ConcurrentAccessResolution::SkipLockedData),
+            _ => None,
+        }
+    }
+}
+
+/// Value of the `CURRENT LOCK TIMEOUT` special register
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LockTimeout {
+    /// Use the `locktimeout` database configuration parameter
+    Default,
+    /// Fail immediately if a lock can not be obtained
+    NoWait,
+    /// Wait until the lock is released
+    WaitForever,
+    /// Wait at most the given number of seconds
+    Seconds(u32),
+}
+
+impl LockTimeout {
+    fn to_sql(self) -> String {
+        match self {
+            LockTimeout::Default => "SET CURRENT LOCK TIMEOUT NULL".to_string(),
+            LockTimeout::NoWait => "SET CURRENT LOCK TIMEOUT NOT WAIT".to_string(),
+            LockTimeout::WaitForever => "SET CURRENT LOCK TIMEOUT WAIT".to_string(),
+            LockTimeout::Seconds(seconds) => format!("SET CURRENT LOCK TIMEOUT {}", seconds),
+        }
+    }
+}
+
+impl<'env, AC: AutocommitMode> Connection<'env, AC> {
+    /// Sets the transaction isolation level (`SQL_ATTR_TXN_ISOLATION`). Must not be called while
+    /// a transaction is open.
+    pub fn set_isolation_level(&mut self, level: IsolationLevel) -> Result<()> {
+        self.set_attr_u32(crate::SQL_ATTR_TXN_ISOLATION, level.to_raw())
+    }
+
+    /// The current transaction isolation level. `None` if the driver reports a level unknown to
+    /// this crate.
+    pub fn isolation_level(&self) -> Result<Option<IsolationLevel>> {
+        self.get_attr_u32(crate::SQL_ATTR_TXN_ISOLATION)
+            .map(IsolationLevel::from_raw)
+    }
+
+    /// Sets the access mode (`SQL_ATTR_ACCESS_MODE`)
+    pub fn set_access_mode(&mut self, mode: AccessMode) -> Result<()> {
+        let value = match mode {
+            AccessMode::ReadWrite => crate::SQL_MODE_READ_WRITE,
+            AccessMode::ReadOnly => crate::SQL_MODE_READ_ONLY,
+        };
+        self.set_attr_u32(crate::SQL_ATTR_ACCESS_MODE, value)
+    }
+
+    /// The current access mode
+    pub fn access_mode(&self) -> Result<AccessMode> {
+        match self.get_attr_u32(crate::SQL_ATTR_ACCESS_MODE)? {
+            crate::SQL_MODE_READ_ONLY => Ok(AccessMode::ReadOnly),
+            _ => Ok(AccessMode::ReadWrite),
+        }
+    }
+
+    /// Sets how access to rows locked by other transactions is resolved
+    /// (`SQL_ATTR_CONCURRENT_ACCESS_RESOLUTION`), e.g. to enable currently committed semantics.
+    pub fn set_concurrent_access_resolution(
+        &mut self,
+        resolution: ConcurrentAccessResolution,
+    ) -> Result<()> {
+        self.set_attr_u32(
+            crate::SQL_ATTR_CONCURRENT_ACCESS_RESOLUTION,
+            resolution.to_raw(),
+        )
+    }
+
+    /// The current concurrent access resolution. `None` if the driver reports a value unknown to
+    /// this crate.
+    pub fn concurrent_access_resolution(&self) -> Result<Option<ConcurrentAccessResolution>> {
+        self.get_attr_u32(crate::SQL_ATTR_CONCURRENT_ACCESS_RESOLUTION)
+            .map(ConcurrentAccessResolution::from_raw)
+    }
+
+    /// Sets the `CURRENT LOCK TIMEOUT` special register. There is no connection attribute for
+    /// the lock timeout, so this executes a `SET` statement.
+    pub fn set_lock_timeout(&mut self, timeout: LockTimeout) -> Result<()> {
+        Statement::with_parent(self)?.exec_direct(&timeout.to_sql())?;
+        Ok(())
+    }
+}
+
+impl<'env> Connection<'env, AutocommitOff> {
+    /// Runs the next transaction with a different isolation level. The previous isolation level is
+    /// restored once the transaction ends via `IsolationOverride::commit` or
+    /// `IsolationOverride::rollback`. If the guard is dropped without ending the transaction, it
+    /// is rolled back.
+    ///
+    /// Must not be called while a transaction is open, i.e. only directly after `commit` or
+    /// `rollback`.
+    pub fn override_isolation_level(
+        &mut self,
+        level: IsolationLevel,
+    ) -> Result<IsolationOverride<'_, 'env>> {
+        let previous = self.get_attr_u32(crate::SQL_ATTR_TXN_ISOLATION)?;
+        self.set_attr_u32(crate::SQL_ATTR_TXN_ISOLATION, level.to_raw())?;
+        Ok(IsolationOverride {
+            conn: self,
+            previous,
+            ended: false,
+            restored: false,
+        })
+    }
+}
+
+/// A transaction running with an overridden isolation level. Dereferences to the connection, so
+/// statements can be executed as usual. See `Connection::override_isolation_level`.
+pub struct IsolationOverride<'c, 'env> {
+    conn: &'c mut Connection<'env, AutocommitOff>,
+    previous: u32,
+    ended: bool,
+    restored: bool,
+}
+
+impl<'c, 'env> IsolationOverride<'c, 'env> {
+    /// Commits the transaction and restores the previous isolation level
+    pub fn commit(mut self) -> Result<()> {
+        self.conn.commit()?;
+        self.ended = true;
+        self.restore()
+    }
+
+    /// Rolls back the transaction and restores the previous isolation level
+    pub fn rollback(mut self) -> Result<()> {
+        self.conn.rollback()?;
+        self.ended = true;
+        self.restore()
+    }
+
+    fn restore(&mut self) -> Result<()> {
+        self.restored = true;
+        self.conn
+            .set_attr_u32(crate::SQL_ATTR_TXN_ISOLATION, self.previous)
+    }
+}
+
+impl<'c, 'env> Deref for IsolationOverride<'c, 'env> {
+    type Target = Connection<'env, AutocommitOff>;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn
+    }
+}
+
+impl<'c, 'env> DerefMut for IsolationOverride<'c, 'env> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn
+    }
+}
+
+impl<'c, 'env> Drop for IsolationOverride<'c, 'env> {
+    fn drop(&mut self) {
+        if !self.ended {
+            if let Err(diag) = self.conn.rollback() {
+                error!("Error rolling back transaction: {}", diag);
+            }
+        }
+        if !self.restored {
+            if let Err(diag) = self.restore() {
+                error!("Error restoring isolation level: {}", diag);
+            }
+        }
+    }
+}
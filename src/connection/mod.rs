@@ -0,0 +1,297 @@
+//! Holds implementation of odbc connection
+mod attributes;
+mod client_info;
+mod functions;
+mod info;
+mod isolation;
+mod polling;
+mod savepoint;
+mod transaction;
+pub use self::client_info::ClientInfo;
+pub use self::functions::{NotSupported, SupportedFunctions};
+pub use self::info::{
+    Codepages, CursorBehavior, CursorCapabilities, IdentifierLengths, IsolationLevels,
+};
+pub use self::isolation::{
+    AccessMode, ConcurrentAccessResolution, IsolationLevel, IsolationOverride, LockTimeout,
+};
+pub use self::savepoint::Savepoint;
+pub use self::transaction::{Transaction, TransactionOutcome};
+use self::info::InfoCache;
+use super::communication_area::{get_sqlca, last_sqlca};
+use super::result::{into_result, into_result_with, CallDiagnostics};
+use super::warnings::Warnings;
+use super::{ffi, safe, DiagnosticRecord, Environment, Handle, Result, Sqlca, Version3};
+use super::{DiagnosticHeader, TextEncoding, WarningPolicy};
+use odbc_safe::{AutocommitMode, AutocommitOff, AutocommitOn};
+use std::cell::RefCell;
+use std::ptr;
+
+/// Represents a connection to an ODBC data source
+#[derive(Debug)]
+pub struct Connection<'env, AC: AutocommitMode> {
+    safe: safe::Connection<'env, AC>,
+    info: RefCell<InfoCache>,
+    warnings: Warnings,
+    encoding: TextEncoding,
+    /// Number of transactions ended by `commit` or `rollback`
+    ended_transactions: u64,
+}
+
+impl<'env, AC: AutocommitMode> Handle for Connection<'env, AC> {
+    type To = ffi::Dbc;
+    unsafe fn handle(&self) -> ffi::SQLHDBC {
+        self.safe.as_raw()
+    }
+}
+
+// Place Constructors into environment, to make them easier to discover
+impl Environment<Version3> {
+    /// Connects to an ODBC data source
+    ///
+    /// # Arguments
+    /// * `dsn` - Data source name configured in the `odbc.ini` file
+    /// * `usr` - User identifier
+    /// * `pwd` - Authentication (usually password)
+    pub fn connect<'env>(
+        &'env self,
+        dsn: &str,
+        usr: &str,
+        pwd: &str,
+    ) -> Result<Connection<'env, AutocommitOn>> {
+        let safe = into_result_with(self, safe::DataSource::with_parent(self.as_safe()))?;
+        let safe = into_result(safe.connect(dsn, usr, pwd), self.encoding())?;
+        self.connected(*safe)
+    }
+
+    /// Connects to an ODBC data source using a connection string
+    ///
+    /// See [SQLDriverConnect][1] for the syntax.
+    /// [1]: https://docs.microsoft.com/en-us/sql/odbc/reference/syntax/sqldriverconnect-function
+    pub fn connect_with_connection_string<'env>(
+        &'env self,
+        connection_str: &str,
+    ) -> Result<Connection<'env, AutocommitOn>> {
+        let safe = into_result_with(self, safe::DataSource::with_parent(self.as_safe()))?;
+        let safe = into_result(
+            safe.connect_with_connection_string(connection_str),
+            self.encoding(),
+        )?;
+        self.connected(*safe)
+    }
+
+    /// Wraps a new connection, detecting its encoding if enabled and applying the client
+    /// information of the environment
+    fn connected<'env>(
+        &'env self,
+        safe: safe::Connection<'env, AutocommitOn>,
+    ) -> Result<Connection<'env, AutocommitOn>> {
+        let mut conn = Connection::from_safe(safe, self.encoding());
+        if self.detects_encoding() {
+            conn.detect_encoding()?;
+        }
+        if let Some(client_info) = self.client_info() {
+            conn.set_client_info(client_info)?;
+        }
+        Ok(conn)
+    }
+}
+
+impl<'env> Connection<'env, AutocommitOn> {
+    #[allow(clippy::result_large_err)]
+    pub fn disable_autocommit(self) -> std::result::Result<Connection<'env, AutocommitOff>, Self> {
+        self.transit(|safe| safe.disable_autocommit())
+    }
+}
+
+impl<'env> Connection<'env, AutocommitOff> {
+    #[allow(clippy::result_large_err)]
+    pub fn enable_autocommit(self) -> std::result::Result<Connection<'env, AutocommitOn>, Self> {
+        self.transit(|safe| safe.enable_autocommit())
+    }
+
+    pub fn commit(&mut self) -> Result<()> {
+        let ret = self.safe.commit();
+        into_result_with(&*self, ret)?;
+        self.ended_transactions += 1;
+        Ok(())
+    }
+
+    pub fn rollback(&mut self) -> Result<()> {
+        let ret = self.safe.rollback();
+        into_result_with(&*self, ret)?;
+        self.ended_transactions += 1;
+        Ok(())
+    }
+}
+
+impl<'env, AC: AutocommitMode> Connection<'env, AC> {
+    fn from_safe(safe: safe::Connection<'env, AC>, encoding: TextEncoding) -> Self {
+        Connection {
+            safe,
+            info: RefCell::new(InfoCache::default()),
+            warnings: Warnings::default(),
+            encoding,
+            ended_transactions: 0,
+        }
+    }
+
+    /// Changes the autocommit mode of the wrapped connection with `switch` and moves the cached
+    /// information and the kept warnings to the connection it returns. `Err` holds the connection
+    /// in its previous mode. Like odbc-safe, it returns the whole connection on failure, which
+    /// is larger than clippy likes for an error.
+    #[allow(clippy::result_large_err)]
+    fn transit<AC2, F>(self, switch: F) -> std::result::Result<Connection<'env, AC2>, Self>
+    where
+        AC2: AutocommitMode,
+        F: FnOnce(
+            safe::Connection<'env, AC>,
+        ) -> safe::Return<safe::Connection<'env, AC2>, safe::Connection<'env, AC>>,
+    {
+        let Connection {
+            safe,
+            info,
+            warnings,
+            encoding,
+            ended_transactions,
+        } = self;
+        match switch(safe) {
+            safe::Return::Success(safe) | safe::Return::Info(safe) => Ok(Connection {
+                safe,
+                info,
+                warnings,
+                encoding,
+                ended_transactions,
+            }),
+            safe::Return::Error(safe) => Err(Connection {
+                safe,
+                info,
+                warnings,
+                encoding,
+                ended_transactions,
+            }),
+        }
+    }
+
+    /// `true` if the data source is set to READ ONLY mode, `false` otherwise.
+    ///
+    /// This characteristic pertains only to the data source itself; it is not characteristic of
+    /// the driver that enables access to the data source. A driver that is read/write can be used
+    /// with a data source that is read-only. If a driver is read-only, all of its data sources
+    /// must be read-only.
+    pub fn is_read_only(&mut self) -> Result<bool> {
+        // The mutability on is_read_only is really an eyesore. Not only to clippy. But we would
+        // have to introduce a cell around `self.safe`, and be careful not to change essential
+        // state in the error path. For now the trouble does not seem worth it.
+        let ret = self.safe.is_read_only();
+        into_result_with(&*self, ret)
+    }
+
+    /// `true` if the connection to the server has been lost (`SQL_ATTR_CONNECTION_DEAD`). This
+    /// only reflects what the driver noticed so far, it does not contact the server.
+    pub fn is_dead(&self) -> Result<bool> {
+        Ok(self.get_attr_u32(crate::SQL_ATTR_CONNECTION_DEAD)? == crate::SQL_CD_TRUE)
+    }
+
+    /// Resets the session to the state of a new connection (`SQL_ATTR_RESET_CONNECTION`). Special
+    /// registers, declared temporary tables and connection attributes like client information
+    /// or the autocommit mode are reset to their defaults.
+    pub fn reset_session(&mut self) -> Result<()> {
+        self.set_attr_u32(
+            crate::SQL_ATTR_RESET_CONNECTION,
+            crate::SQL_RESET_CONNECTION_YES,
+        )
+    }
+
+    /// Warnings reported by the calls on this connection since they have last been taken, in the
+    /// order they occurred. Only the 64 most recent ones are kept.
+    pub fn warnings(&self) -> Vec<DiagnosticRecord> {
+        self.warnings.get()
+    }
+
+    /// Returns the warnings like `warnings` and clears them
+    pub fn take_warnings(&self) -> Vec<DiagnosticRecord> {
+        self.warnings.take()
+    }
+
+    /// Policy deciding which warnings of this connection are promoted to errors. Statements
+    /// allocated on this connection start with a copy of it.
+    pub fn warning_policy(&self) -> &WarningPolicy {
+        self.warnings.policy()
+    }
+
+    /// Sets the policy deciding which warnings are promoted to errors, see `WarningPolicy`
+    pub fn set_warning_policy(&mut self, policy: WarningPolicy) {
+        self.warnings.set_policy(policy);
+    }
+
+    /// Encoding of this connection. Initially the encoding of its environment.
+    pub fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+
+    /// Sets the encoding of this connection and of the statements allocated from now on, see
+    /// `TextEncoding`
+    pub fn set_encoding(&mut self, encoding: TextEncoding) {
+        self.encoding = encoding;
+    }
+
+    /// Sets the encoding of this connection from the application code page reported by the
+    /// client, see `codepages`, and returns it. The client converts character data from the
+    /// database code page to the application code page, so the latter is used for both encodings.
+    ///
+    /// Whether the wide functions are used is kept, see `TextEncoding::with_wide_api`. Fails if the
+    /// code page has no matching encoding, e.g. for EBCDIC code pages.
+    pub fn detect_encoding(&mut self) -> Result<TextEncoding> {
+        let codepages = self.codepages()?;
+        debug!("Detected code pages {:?}", codepages);
+        let wide = self.encoding.is_wide();
+        self.encoding = TextEncoding::from_ccsid(codepages.application)?.with_wide_api(wide);
+        Ok(self.encoding)
+    }
+
+    /// SQL communication area of the last SQL statement processed on this connection, e.g. a
+    /// `COMMIT`. Use `Statement::sqlca` for statements executed with a statement handle.
+    pub fn sqlca(&self) -> Result<Sqlca> {
+        get_sqlca(unsafe { self.handle() }, ptr::null_mut(), self.encoding.os()).into_result(self)
+    }
+
+    /// Header fields of the diagnostic data of the last call on this connection
+    pub fn diagnostic_header(&self) -> DiagnosticHeader {
+        let handle = safe::Handle::handle(self);
+        DiagnosticHeader::read(ffi::SQL_HANDLE_DBC, handle, self.encoding.os())
+    }
+
+    /// Closes the connection to the data source. If not called explicitly the disconnect will be
+    /// invoked implicitly by `drop()`
+    pub fn disconnect(self) -> Result<()> {
+        into_result(self.safe.disconnect(), self.encoding)?;
+        Ok(())
+    }
+}
+
+impl<'env, AC: AutocommitMode> CallDiagnostics for Connection<'env, AC> {
+    fn warning_store(&self) -> Option<&Warnings> {
+        Some(&self.warnings)
+    }
+
+    fn last_sqlca(&self) -> Option<Sqlca> {
+        last_sqlca(unsafe { self.handle() }, ptr::null_mut(), self.encoding.os())
+    }
+
+    fn diag_handle(&self) -> Option<(ffi::HandleType, ffi::SQLHANDLE)> {
+        Some((ffi::SQL_HANDLE_DBC, safe::Handle::handle(self)))
+    }
+
+    fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+}
+
+unsafe impl<'env, AC: AutocommitMode> safe::Handle for Connection<'env, AC> {
+    const HANDLE_TYPE: ffi::HandleType = ffi::SQL_HANDLE_DBC;
+
+    fn handle(&self) -> ffi::SQLHANDLE {
+        self.safe.as_raw() as ffi::SQLHANDLE
+    }
+}
@@ -0,0 +1,66 @@
+//! Native asynchronous execution on the connection level
+use super::super::{ffi, Handle, Result, Return};
+use super::Connection;
+use odbc_safe::{AutocommitMode, AutocommitOff};
+use std::task::Poll;
+
+impl<'env, AC: AutocommitMode> Connection<'env, AC> {
+    /// Enables or disables asynchronous execution for all statements allocated on this
+    /// connection (`SQL_ATTR_ASYNC_ENABLE`). Statements then have to be driven with the polling
+    /// functions like `Statement::poll_exec_direct`.
+    pub fn set_async_enable(&mut self, enabled: bool) -> Result<()> {
+        let value = if enabled {
+            crate::SQL_ASYNC_ENABLE_ON
+        } else {
+            crate::SQL_ASYNC_ENABLE_OFF
+        };
+        self.set_attr_u32(crate::SQL_ATTR_ASYNC_ENABLE, value)
+    }
+
+    /// Enables or disables asynchronous execution of connection functions
+    /// (`SQL_ATTR_ASYNC_DBC_FUNCTIONS_ENABLE`). While enabled, ending transactions has to be
+    /// polled with `poll_commit` and `poll_rollback`. Disable it again before disconnecting.
+    pub fn set_async_dbc_functions_enable(&mut self, enabled: bool) -> Result<()> {
+        let value = if enabled {
+            crate::SQL_ASYNC_DBC_ENABLE_ON
+        } else {
+            crate::SQL_ASYNC_DBC_ENABLE_OFF
+        };
+        self.set_attr_u32(crate::SQL_ATTR_ASYNC_DBC_FUNCTIONS_ENABLE, value)
+    }
+
+    pub(crate) fn end_tran(&self, completion_type: u32) -> Return<()> {
+        let completion_type = if completion_type == crate::SQL_COMMIT {
+            ffi::SqlCompletionType::SQL_COMMIT
+        } else {
+            ffi::SqlCompletionType::SQL_ROLLBACK
+        };
+        match unsafe {
+            ffi::SQLEndTran(
+                ffi::SQL_HANDLE_DBC,
+                self.handle() as ffi::SQLHANDLE,
+                completion_type,
+            )
+        } {
+            ffi::SQL_SUCCESS => Return::Success(()),
+            ffi::SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(()),
+            ffi::SQL_ERROR => Return::Error,
+            ffi::SQL_STILL_EXECUTING => Return::StillExecuting,
+            r => Return::unexpected("SQLEndTran", r),
+        }
+    }
+}
+
+impl<'env> Connection<'env, AutocommitOff> {
+    /// Like `commit`, but returns `Poll::Pending` if the commit has been started asynchronously
+    /// and is not complete yet. Call again until the result is ready.
+    pub fn poll_commit(&mut self) -> Result<Poll<()>> {
+        self.end_tran(crate::SQL_COMMIT).into_poll(&*self)
+    }
+
+    /// Like `rollback`, but returns `Poll::Pending` if the rollback has been started
+    /// asynchronously and is not complete yet. Call again until the result is ready.
+    pub fn poll_rollback(&mut self) -> Result<Poll<()>> {
+        self.end_tran(crate::SQL_ROLLBACK).into_poll(&*self)
+    }
+}
@@ -0,0 +1,171 @@
+//! Savepoints within a transaction of a connection with autocommit disabled
+use super::super::{Result, Statement};
+use super::Connection;
+use odbc_safe::{AutocommitMode, AutocommitOff};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Suffix appended to savepoint names, so nested or repeated savepoints never collide
+static NEXT_SAVEPOINT: AtomicUsize = AtomicUsize::new(0);
+
+impl<'env> Connection<'env, AutocommitOff> {
+    /// Sets a savepoint in the current transaction. `name` is used as a prefix of the savepoint
+    /// name, a unique suffix is appended by the library.
+    ///
+    /// The returned guard dereferences to the connection. If it is dropped without calling
+    /// `Savepoint::release` or ending the transaction, the transaction is rolled back to the
+    /// savepoint. Savepoints can be nested by calling `savepoint` on the guard.
+    pub fn savepoint(&mut self, name: &str) -> Result<Savepoint<'_, 'env>> {
+        self.set_savepoint(name)
+    }
+}
+
+impl<'env, AC: AutocommitMode> Connection<'env, AC> {
+    /// Sets a savepoint, see `savepoint`. The caller makes sure autocommit is disabled.
+    pub(crate) fn set_savepoint(&mut self, name: &str) -> Result<Savepoint<'_, 'env, AC>> {
+        let name = format!(
+            "{}_{}",
+            name,
+            NEXT_SAVEPOINT.fetch_add(1, Ordering::Relaxed)
+        );
+        execute(
+            self,
+            &format!(
+                "SAVEPOINT {} ON ROLLBACK RETAIN CURSORS",
+                quote_identifier(&name)
+            ),
+        )?;
+        let transaction = self.ended_transactions;
+        Ok(Savepoint {
+            conn: self,
+            name,
+            transaction,
+            released: false,
+        })
+    }
+}
+
+/// A savepoint in the current transaction. See `Connection::savepoint` and
+/// `Transaction::savepoint`.
+pub struct Savepoint<'c, 'env, AC: AutocommitMode = AutocommitOff> {
+    conn: &'c mut Connection<'env, AC>,
+    name: String,
+    /// Value of `Connection::ended_transactions` when the savepoint was set
+    transaction: u64,
+    released: bool,
+}
+
+impl<'c, 'env, AC: AutocommitMode> Savepoint<'c, 'env, AC> {
+    /// Name of the savepoint, including the generated suffix
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Releases the savepoint, keeping all changes made since it was set. Savepoints nested
+    /// within this one are released as well.
+    pub fn release(mut self) -> Result<()> {
+        self.release_savepoint()
+    }
+
+    /// Releases the savepoint and commits the transaction it belongs to
+    pub fn commit(mut self) -> Result<()> {
+        self.release_savepoint()?;
+        self.conn.end_transaction(crate::SQL_COMMIT)
+    }
+
+    /// Rolls back the whole transaction the savepoint belongs to
+    pub fn rollback(self) -> Result<()> {
+        self.conn.end_transaction(crate::SQL_ROLLBACK)
+    }
+
+    /// Undoes all changes made since the savepoint was set. The savepoint stays active, so the
+    /// work can be retried.
+    pub fn rollback_to(&mut self) -> Result<()> {
+        execute(
+            self.conn,
+            &format!("ROLLBACK TO SAVEPOINT {}", quote_identifier(&self.name)),
+        )
+    }
+
+    fn release_savepoint(&mut self) -> Result<()> {
+        execute(
+            self.conn,
+            &format!("RELEASE SAVEPOINT {}", quote_identifier(&self.name)),
+        )?;
+        self.released = true;
+        Ok(())
+    }
+}
+
+impl<'c, 'env, AC: AutocommitMode> Deref for Savepoint<'c, 'env, AC> {
+    type Target = Connection<'env, AC>;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn
+    }
+}
+
+impl<'c, 'env, AC: AutocommitMode> DerefMut for Savepoint<'c, 'env, AC> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn
+    }
+}
+
+impl<'c, 'env, AC: AutocommitMode> Drop for Savepoint<'c, 'env, AC> {
+    fn drop(&mut self) {
+        if rolls_back_on_drop(
+            self.released,
+            self.transaction,
+            self.conn.ended_transactions,
+        ) {
+            if let Err(e) = self.rollback_to() {
+                error!("Error rolling back to savepoint {}: {}", self.name, e);
+            }
+        }
+    }
+}
+
+/// `true` if a savepoint set while `transaction` transactions had been ended is still active and
+/// has to be rolled back to. Committing or rolling back the transaction through the connection
+/// discards the savepoint, so there is nothing left to roll back to.
+fn rolls_back_on_drop(released: bool, transaction: u64, ended_transactions: u64) -> bool {
+    !released && ended_transactions == transaction
+}
+
+fn execute<AC: AutocommitMode>(conn: &Connection<AC>, sql: &str) -> Result<()> {
+    Statement::with_parent(conn)?.exec_direct(sql)?;
+    Ok(())
+}
+
+/// Quotes `name` as a delimited identifier, so it may contain any character
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn quote() {
+        assert_eq!(quote_identifier("BATCH_1"), "\"BATCH_1\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn drop_rolls_back_active_savepoint() {
+        assert!(rolls_back_on_drop(false, 3, 3));
+    }
+
+    #[test]
+    fn drop_keeps_released_savepoint() {
+        assert!(!rolls_back_on_drop(true, 3, 3));
+    }
+
+    #[test]
+    fn drop_ignores_savepoint_of_ended_transaction() {
+        // `commit` or `rollback` on the connection counted another ended transaction
+        assert!(!rolls_back_on_drop(false, 3, 4));
+    }
+}
@@ -0,0 +1,219 @@
+//! Scoped transactions which commit or roll back depending on the outcome of a closure
+use super::super::{Error, Result, RetryPolicy};
+use super::{Connection, Savepoint};
+use odbc_safe::{AutocommitMode, AutocommitOff, AutocommitOn};
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+
+/// Outcome of `Connection::transaction` on a connection in autocommit mode
+#[must_use]
+#[derive(Debug)]
+pub struct TransactionOutcome<T, E> {
+    /// Result of the closure. `Ok` if the transaction has been committed.
+    pub result: std::result::Result<T, E>,
+    /// Error enabling autocommit again after the transaction ended. The connection keeps
+    /// autocommit disabled in that case, so later statements are not committed automatically.
+    pub restore_error: Option<Error>,
+}
+
+impl<T, E: From<Error>> TransactionOutcome<T, E> {
+    /// The result of the closure, unless autocommit could not be enabled again. The error is
+    /// returned then, even though the transaction may have been committed.
+    pub fn into_result(self) -> std::result::Result<T, E> {
+        match self.restore_error {
+            Some(error) => {
+                self.result?;
+                Err(error.into())
+            }
+            None => self.result,
+        }
+    }
+}
+
+/// A transaction on a connection in autocommit mode, see `Connection::transaction`
+///
+/// Dereferences to the connection, which has autocommit disabled until the transaction ends.
+pub struct Transaction<'c, 'env> {
+    conn: &'c mut Connection<'env, AutocommitOn>,
+}
+
+impl<'c, 'env> Transaction<'c, 'env> {
+    /// Sets a savepoint in this transaction, see `Connection::savepoint`
+    pub fn savepoint(&mut self, name: &str) -> Result<Savepoint<'_, 'env, AutocommitOn>> {
+        self.conn.set_savepoint(name)
+    }
+}
+
+impl<'c, 'env> Deref for Transaction<'c, 'env> {
+    type Target = Connection<'env, AutocommitOn>;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn
+    }
+}
+
+impl<'c, 'env> DerefMut for Transaction<'c, 'env> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn
+    }
+}
+
+impl<'env> Connection<'env, AutocommitOn> {
+    /// Runs `f` within a transaction. Autocommit is disabled on the connection for the duration
+    /// of the closure. The transaction is committed if `f` returns `Ok` and rolled back if it
+    /// returns `Err` or panics. Autocommit is enabled again afterwards in every case, see
+    /// `TransactionOutcome`.
+    pub fn transaction<T, E, F>(&mut self, f: F) -> TransactionOutcome<T, E>
+    where
+        F: FnOnce(&mut Transaction<'_, 'env>) -> std::result::Result<T, E>,
+        E: From<Error>,
+    {
+        if let Err(diag) = self.set_attr_u32(crate::SQL_ATTR_AUTOCOMMIT, crate::SQL_AUTOCOMMIT_OFF)
+        {
+            return TransactionOutcome {
+                result: Err(diag.into()),
+                restore_error: None,
+            };
+        }
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+            let result = f(&mut Transaction { conn: &mut *self });
+            self.end_transaction_with(result)
+        }));
+        if outcome.is_err() {
+            if let Err(diag) = self.end_transaction(crate::SQL_ROLLBACK) {
+                error!("Error rolling back transaction after panic: {}", diag);
+            }
+        }
+        let restored = self.set_attr_u32(crate::SQL_ATTR_AUTOCOMMIT, crate::SQL_AUTOCOMMIT_ON);
+        match outcome {
+            Ok(result) => TransactionOutcome {
+                result,
+                restore_error: restored.err(),
+            },
+            Err(payload) => {
+                if let Err(diag) = restored {
+                    error!("Error enabling autocommit after panic: {}", diag);
+                }
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Like `transaction`, but runs `f` again in a new transaction if it fails with an error which
+    /// `policy` considers transient, e.g. a deadlock
+    pub fn transaction_with_retry<T, F>(
+        &mut self,
+        policy: &RetryPolicy,
+        mut f: F,
+    ) -> TransactionOutcome<T, Error>
+    where
+        F: FnMut(&mut Transaction<'_, 'env>) -> Result<T>,
+    {
+        let mut attempt = 1;
+        loop {
+            let outcome = self.transaction(|tx| f(tx));
+            match outcome.result {
+                Err(ref error)
+                    if outcome.restore_error.is_none() && policy.should_retry(error, attempt) =>
+                {
+                    policy.wait(error, attempt);
+                    attempt += 1;
+                }
+                result => {
+                    return TransactionOutcome {
+                        result: policy.finish(result, attempt),
+                        restore_error: outcome.restore_error,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'env, AC: AutocommitMode> Connection<'env, AC> {
+    /// Commits (`SQL_COMMIT`) or rolls back (`SQL_ROLLBACK`) the current transaction on the
+    /// handle, regardless of the autocommit mode tracked by the type
+    pub(crate) fn end_transaction(&mut self, completion_type: u32) -> Result<()> {
+        self.end_tran(completion_type).into_result(&*self)?;
+        self.ended_transactions += 1;
+        Ok(())
+    }
+
+    /// Commits the transaction if `result` is `Ok` and rolls it back otherwise. A failed commit
+    /// is rolled back and returned as the error.
+    fn end_transaction_with<T, E>(
+        &mut self,
+        result: std::result::Result<T, E>,
+    ) -> std::result::Result<T, E>
+    where
+        E: From<Error>,
+    {
+        match result {
+            Ok(value) => {
+                if let Err(diag) = self.end_transaction(crate::SQL_COMMIT) {
+                    if let Err(diag) = self.end_transaction(crate::SQL_ROLLBACK) {
+                        error!(
+                            "Error rolling back transaction after failed commit: {}",
+                            diag
+                        );
+                    }
+                    return Err(diag.into());
+                }
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(diag) = self.end_transaction(crate::SQL_ROLLBACK) {
+                    error!("Error rolling back transaction: {}", diag);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<'env> Connection<'env, AutocommitOff> {
+    /// Runs `f` within a transaction. The transaction is committed if `f` returns `Ok` and rolled
+    /// back if it returns `Err` or panics.
+    pub fn transaction<T, E, F>(&mut self, f: F) -> std::result::Result<T, E>
+    where
+        F: FnOnce(&mut Connection<'env, AutocommitOff>) -> std::result::Result<T, E>,
+        E: From<Error>,
+    {
+        match panic::catch_unwind(AssertUnwindSafe(|| {
+            let result = f(self);
+            self.end_transaction_with(result)
+        })) {
+            Ok(result) => result,
+            Err(payload) => {
+                if let Err(diag) = self.rollback() {
+                    error!("Error rolling back transaction after panic: {}", diag);
+                }
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn into_result_keeps_closure_error() {
+        let outcome: TransactionOutcome<(), Error> = TransactionOutcome {
+            result: Err(Error::other("closure")),
+            restore_error: Some(Error::other("restore")),
+        };
+        assert_eq!(outcome.into_result().unwrap_err().to_string(), "closure");
+    }
+
+    #[test]
+    fn into_result_reports_restore_error() {
+        let outcome: TransactionOutcome<i32, Error> = TransactionOutcome {
+            result: Ok(42),
+            restore_error: Some(Error::other("restore")),
+        };
+        assert_eq!(outcome.into_result().unwrap_err().to_string(), "restore");
+    }
+}
@@ -0,0 +1,446 @@
+//! Building and parsing DB2 CLI connection strings
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Keywords whose values are replaced by `***` when a `ConnectionString` is displayed
+const SECRET_KEYWORDS: &[&str] = &[
+    "PWD",
+    "NEWPWD",
+    "SSLCLIENTKEYSTASH",
+    "SSLCLIENTKEYSTOREDBPASSWORD",
+];
+
+/// Keywords known to DB2 CLI. Parsing a connection string with a keyword not in this list logs a
+/// warning, as DB2 silently ignores unknown keywords.
+const KNOWN_KEYWORDS: &[&str] = &[
+    "DSN",
+    "DRIVER",
+    "FILEDSN",
+    "DATABASE",
+    "HOSTNAME",
+    "PORT",
+    "PROTOCOL",
+    "UID",
+    "PWD",
+    "NEWPWD",
+    "SECURITY",
+    "SSLSERVERCERTIFICATE",
+    "SSLCLIENTKEYSTOREDB",
+    "SSLCLIENTKEYSTOREDBPASSWORD",
+    "SSLCLIENTKEYSTASH",
+    "SSLCLIENTLABEL",
+    "CURRENTSCHEMA",
+    "CURRENTSQLID",
+    "CURRENTFUNCTIONPATH",
+    "AUTHENTICATION",
+    "CONNECTTIMEOUT",
+    "QUERYTIMEOUTINTERVAL",
+    "KEEPALIVETIMEOUT",
+    "AUTOCOMMIT",
+    "TXNISOLATION",
+    "CURRENTPACKAGESET",
+    "CLIENTUSERID",
+    "CLIENTWRKSTNNAME",
+    "CLIENTAPPLNAME",
+    "CLIENTACCTSTR",
+    "APPLICATIONNAME",
+    "ALTERNATEHOSTNAME",
+    "ALTERNATEPORT",
+    "ENABLEACR",
+    "ENABLESEAMLESSACR",
+    "ENABLEWLB",
+    "TRUSTEDCONTEXT",
+    "KRBPLUGIN",
+    "GSSPLUGIN",
+    "PLUGINNAME",
+    "DBALIAS",
+    "PATCH1",
+    "PATCH2",
+    "LOBMAXCOLUMNSIZE",
+    "LONGDATACOMPAT",
+    "DEFERREDPREPARE",
+    "CURSORHOLD",
+];
+
+/// Communication protocol used to reach the server (`PROTOCOL`)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Protocol {
+    /// `TCPIP`, IPv4 or IPv6
+    TcpIp,
+    /// `TCPIP4`, IPv4 only
+    TcpIp4,
+    /// `TCPIP6`, IPv6 only
+    TcpIp6,
+    /// `IPC`, local connection through interprocess communication
+    Ipc,
+    /// `LOCAL`, local connection to a catalogued database
+    Local,
+}
+
+impl Protocol {
+    fn as_str(self) -> &'static str {
+        match self {
+            Protocol::TcpIp => "TCPIP",
+            Protocol::TcpIp4 => "TCPIP4",
+            Protocol::TcpIp6 => "TCPIP6",
+            Protocol::Ipc => "IPC",
+            Protocol::Local => "LOCAL",
+        }
+    }
+}
+
+/// Authentication type requested from the server (`AUTHENTICATION`)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Authentication {
+    Server,
+    ServerEncrypt,
+    ServerEncryptAes,
+    DataEncrypt,
+    Kerberos,
+    GssPlugin,
+    Certificate,
+}
+
+impl Authentication {
+    fn as_str(self) -> &'static str {
+        match self {
+            Authentication::Server => "SERVER",
+            Authentication::ServerEncrypt => "SERVER_ENCRYPT",
+            Authentication::ServerEncryptAes => "SERVER_ENCRYPT_AES",
+            Authentication::DataEncrypt => "DATA_ENCRYPT",
+            Authentication::Kerberos => "KERBEROS",
+            Authentication::GssPlugin => "GSSPLUGIN",
+            Authentication::Certificate => "CERTIFICATE",
+        }
+    }
+}
+
+/// A DB2 CLI connection string, e.g. `DATABASE=sample;HOSTNAME=localhost;PORT=50000;...`
+///
+/// Values are escaped with braces where necessary, so passwords may contain `;`, `{` or `}`.
+/// Keywords are case insensitive, setting a keyword twice replaces the previous value.
+///
+/// `Display` replaces passwords with `***`, so a `ConnectionString` can be logged safely. Use
+/// `ConnectionString::expose` to obtain the string to connect with.
+///
+/// ```
+/// use ibm_db::{ConnectionString, Protocol};
+///
+/// let conn_str = ConnectionString::new()
+///     .database("sample")
+///     .hostname("localhost")
+///     .port(50000)
+///     .protocol(Protocol::TcpIp)
+///     .uid("db2inst1")
+///     .pwd("se;cr}t");
+/// assert_eq!(
+///     conn_str.expose(),
+///     "DATABASE=sample;HOSTNAME=localhost;PORT=50000;PROTOCOL=TCPIP;UID=db2inst1;PWD={se;cr}}t};"
+/// );
+/// assert_eq!(
+///     conn_str.to_string(),
+///     "DATABASE=sample;HOSTNAME=localhost;PORT=50000;PROTOCOL=TCPIP;UID=db2inst1;PWD=***;"
+/// );
+/// ```
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct ConnectionString {
+    attributes: Vec<(String, String)>,
+}
+
+impl ConnectionString {
+    /// Creates an empty connection string
+    pub fn new() -> ConnectionString {
+        ConnectionString::default()
+    }
+
+    /// Parses a connection string. Values may be enclosed in braces, with `}}` standing for a
+    /// literal `}`. Unknown keywords are kept, but logged as a warning.
+    pub fn parse(connection_string: &str) -> Result<ConnectionString, ParseError> {
+        let chars: Vec<char> = connection_string.chars().collect();
+        let mut result = ConnectionString::new();
+        let mut pos = 0;
+        loop {
+            while pos < chars.len() && (chars[pos] == ';' || chars[pos].is_whitespace()) {
+                pos += 1;
+            }
+            if pos == chars.len() {
+                break;
+            }
+            let key_start = pos;
+            while pos < chars.len() && chars[pos] != '=' {
+                if chars[pos] == ';' {
+                    return Err(ParseError::new(key_start, "keyword without value"));
+                }
+                pos += 1;
+            }
+            if pos == chars.len() {
+                return Err(ParseError::new(key_start, "keyword without value"));
+            }
+            let key: String = chars[key_start..pos].iter().collect();
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(ParseError::new(key_start, "missing keyword"));
+            }
+            // Skip '='
+            pos += 1;
+            while pos < chars.len() && chars[pos] == ' ' {
+                pos += 1;
+            }
+            let value = if pos < chars.len() && chars[pos] == '{' {
+                let value_start = pos;
+                pos += 1;
+                let mut value = String::new();
+                loop {
+                    match chars.get(pos) {
+                        None => {
+                            return Err(ParseError::new(value_start, "unterminated braces"));
+                        }
+                        Some(&'}') if chars.get(pos + 1) == Some(&'}') => {
+                            value.push('}');
+                            pos += 2;
+                        }
+                        Some(&'}') => {
+                            pos += 1;
+                            break;
+                        }
+                        Some(&c) => {
+                            value.push(c);
+                            pos += 1;
+                        }
+                    }
+                }
+                while pos < chars.len() && chars[pos].is_whitespace() {
+                    pos += 1;
+                }
+                if pos < chars.len() && chars[pos] != ';' {
+                    return Err(ParseError::new(pos, "expected ';' after closing brace"));
+                }
+                value
+            } else {
+                let value_start = pos;
+                while pos < chars.len() && chars[pos] != ';' {
+                    pos += 1;
+                }
+                let value: String = chars[value_start..pos].iter().collect();
+                value.trim_end().to_string()
+            };
+            if !KNOWN_KEYWORDS.contains(&key.to_uppercase().as_str()) {
+                warn!("Unknown connection string keyword: {}", key);
+            }
+            result = result.set(key, value);
+        }
+        Ok(result)
+    }
+
+    /// Sets an arbitrary keyword. Prefer the typed setters for the keywords they cover.
+    pub fn set<K: Into<String>, V: Into<String>>(mut self, keyword: K, value: V) -> Self {
+        let keyword = keyword.into();
+        let value = value.into();
+        match self
+            .attributes
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case(&keyword))
+        {
+            Some(attribute) => attribute.1 = value,
+            None => self.attributes.push((keyword, value)),
+        }
+        self
+    }
+
+    /// The value of a keyword, compared case insensitively
+    pub fn get(&self, keyword: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(keyword))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Removes a keyword
+    pub fn remove(mut self, keyword: &str) -> Self {
+        self.attributes.retain(|(k, _)| !k.eq_ignore_ascii_case(keyword));
+        self
+    }
+
+    /// Name of a data source catalogued in `db2cli.ini` or `db2dsdriver.cfg`
+    pub fn dsn<S: Into<String>>(self, dsn: S) -> Self {
+        self.set("DSN", dsn)
+    }
+
+    /// Name of the driver, e.g. `{IBM DB2 ODBC DRIVER}`
+    pub fn driver<S: Into<String>>(self, driver: S) -> Self {
+        self.set("DRIVER", driver)
+    }
+
+    pub fn database<S: Into<String>>(self, database: S) -> Self {
+        self.set("DATABASE", database)
+    }
+
+    pub fn hostname<S: Into<String>>(self, hostname: S) -> Self {
+        self.set("HOSTNAME", hostname)
+    }
+
+    pub fn port(self, port: u16) -> Self {
+        self.set("PORT", port.to_string())
+    }
+
+    pub fn protocol(self, protocol: Protocol) -> Self {
+        self.set("PROTOCOL", protocol.as_str())
+    }
+
+    pub fn uid<S: Into<String>>(self, uid: S) -> Self {
+        self.set("UID", uid)
+    }
+
+    pub fn pwd<S: Into<String>>(self, pwd: S) -> Self {
+        self.set("PWD", pwd)
+    }
+
+    /// Encrypts the connection with SSL/TLS (`SECURITY=SSL`)
+    pub fn ssl(self) -> Self {
+        self.set("SECURITY", "SSL")
+    }
+
+    /// Path of the certificate used to verify the server (`SSLServerCertificate`)
+    pub fn ssl_server_certificate<S: Into<String>>(self, path: S) -> Self {
+        self.set("SSLServerCertificate", path)
+    }
+
+    /// Default schema of unqualified objects (`CurrentSchema`)
+    pub fn current_schema<S: Into<String>>(self, schema: S) -> Self {
+        self.set("CurrentSchema", schema)
+    }
+
+    pub fn authentication(self, authentication: Authentication) -> Self {
+        self.set("Authentication", authentication.as_str())
+    }
+
+    /// Seconds to wait for the connection to be established (`ConnectTimeout`)
+    pub fn connect_timeout(self, seconds: u32) -> Self {
+        self.set("ConnectTimeout", seconds.to_string())
+    }
+
+    /// The connection string including passwords, to be passed to
+    /// `Environment::connect_with_connection_string`
+    pub fn expose(&self) -> String {
+        self.format(false)
+    }
+
+    fn format(&self, redact: bool) -> String {
+        let mut result = String::new();
+        for (keyword, value) in &self.attributes {
+            result.push_str(keyword);
+            result.push('=');
+            if redact && SECRET_KEYWORDS.contains(&keyword.to_uppercase().as_str()) {
+                result.push_str("***");
+            } else if needs_braces(value) {
+                result.push('{');
+                result.push_str(&value.replace('}', "}}"));
+                result.push('}');
+            } else {
+                result.push_str(value);
+            }
+            result.push(';');
+        }
+        result
+    }
+}
+
+/// Values containing separators or braces, or with surrounding whitespace have to be escaped
+fn needs_braces(value: &str) -> bool {
+    value.contains([';', '{', '}']) || value.trim() != value
+}
+
+impl fmt::Display for ConnectionString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.format(true))
+    }
+}
+
+impl fmt::Debug for ConnectionString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ConnectionString")
+            .field(&self.format(true))
+            .finish()
+    }
+}
+
+impl FromStr for ConnectionString {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<ConnectionString, ParseError> {
+        ConnectionString::parse(s)
+    }
+}
+
+impl From<ConnectionString> for String {
+    fn from(connection_string: ConnectionString) -> String {
+        connection_string.expose()
+    }
+}
+
+/// Error returned when parsing a malformed connection string
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    /// Character offset at which the error was detected
+    pub position: usize,
+    message: &'static str,
+}
+
+impl ParseError {
+    fn new(position: usize, message: &'static str) -> ParseError {
+        ParseError { position, message }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid connection string at position {}: {}",
+            self.position, self.message
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn escapes_values() {
+        let conn_str = ConnectionString::new().uid("user").pwd("a;b}c");
+        assert_eq!(conn_str.expose(), "UID=user;PWD={a;b}}c};");
+        assert_eq!(conn_str.to_string(), "UID=user;PWD=***;");
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        let conn_str = ConnectionString::parse(
+            "database=sample; HOSTNAME=db.example.com;PORT=50000;PWD={a;b}}c} ;",
+        )
+        .unwrap();
+        assert_eq!(conn_str.get("DATABASE"), Some("sample"));
+        assert_eq!(conn_str.get("hostname"), Some("db.example.com"));
+        assert_eq!(conn_str.get("pwd"), Some("a;b}c"));
+        assert_eq!(ConnectionString::parse(&conn_str.expose()).unwrap(), conn_str);
+    }
+
+    #[test]
+    fn set_replaces_case_insensitive() {
+        let conn_str = ConnectionString::parse("hostname=a")
+            .unwrap()
+            .hostname("b");
+        assert_eq!(conn_str.expose(), "hostname=b;");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(ConnectionString::parse("UID").unwrap_err().position, 0);
+        assert_eq!(ConnectionString::parse("PWD={abc").unwrap_err().position, 4);
+        assert_eq!(ConnectionString::parse("PWD={a}b").unwrap_err().position, 7);
+    }
+}
@@ -1,7 +1,9 @@
-use super::{ffi, safe};
+use super::encoding::from_wide;
+use super::{ffi, safe, Return};
+use encoding_rs::Encoding;
 use std::error::Error;
 use std::ffi::CStr;
-use std::{cmp, fmt};
+use std::{cmp, fmt, ptr};
 
 pub const MAX_DIAGNOSTIC_MESSAGE_SIZE: usize = 1024;
 
@@ -9,6 +11,7 @@ pub const MAX_DIAGNOSTIC_MESSAGE_SIZE: usize = 1024;
 ///
 /// The `description` method of the `std::error::Error` trait only returns the message. Use
 /// `std::fmt::Display` to retrieve status code and other information.
+#[derive(Clone)]
 pub struct DiagnosticRecord {
     // All elements but the last one, may not be null. The last one must be null.
     state: [ffi::SQLCHAR; ffi::SQL_SQLSTATE_SIZE + 1],
@@ -18,6 +21,9 @@ pub struct DiagnosticRecord {
     message_length: ffi::SQLSMALLINT,
     native_error: ffi::SQLINTEGER,
     message_string: String,
+    // Row and column of the failing row or parameter, only read for statements
+    row_number: Option<i64>,
+    column_number: Option<i32>,
 }
 
 impl DiagnosticRecord {
@@ -25,14 +31,74 @@ impl DiagnosticRecord {
     pub fn get_raw_state(&self) -> &[u8] {
         &self.state
     }
-    /// get raw diagnostics message for avoiding encoding error.
+    /// get raw diagnostics message for avoiding encoding error. Unlike `get_message` it is
+    /// truncated to `MAX_DIAGNOSTIC_MESSAGE_SIZE` bytes.
     pub fn get_raw_message(&self) -> &[u8] {
         &self.message[0..self.message_length as usize]
     }
+    /// get diagnostics message, decoded with the OS encoding of the statement, connection or
+    /// environment it has been reported for.
+    pub fn get_message(&self) -> &str {
+        &self.message_string
+    }
     /// get native odbc error number
     pub fn get_native_error(&self) -> i32 {
         self.native_error
     }
+    /// get the number of the row in the rowset or the parameter set the record refers to
+    /// (`SQL_DIAG_ROW_NUMBER`). Starts at one, `None` if the record is not associated with a row.
+    pub fn get_row_number(&self) -> Option<i64> {
+        self.row_number
+    }
+    /// get the number of the column or parameter the record refers to
+    /// (`SQL_DIAG_COLUMN_NUMBER`). Starts at one, `None` if the record is not associated with a
+    /// column.
+    pub fn get_column_number(&self) -> Option<i32> {
+        self.column_number
+    }
+    /// reads the row and column number of the record from a statement handle
+    pub(crate) fn read_position(
+        &mut self,
+        handle_type: ffi::HandleType,
+        handle: ffi::SQLHANDLE,
+        record_number: i16,
+    ) {
+        let row = crate::SQL_DIAG_ROW_NUMBER;
+        self.row_number = diag_field::<ffi::SQLLEN>(handle_type, handle, record_number, row)
+            .filter(|&row| row > 0);
+        let column = crate::SQL_DIAG_COLUMN_NUMBER;
+        self.column_number =
+            diag_field::<ffi::SQLINTEGER>(handle_type, handle, record_number, column)
+                .filter(|&column| column > 0);
+    }
+    /// constructs a record from a decoded message, e.g. one read with `SQLGetDiagRecW`. The raw
+    /// message holds the UTF-8 encoded text. SQLSTATEs consist of ASCII characters only, others
+    /// are replaced by `?`.
+    pub(crate) fn from_text(
+        state: &[u16],
+        native_error: ffi::SQLINTEGER,
+        message: String,
+    ) -> DiagnosticRecord {
+        let mut rec = DiagnosticRecord {
+            state: [0u8; ffi::SQL_SQLSTATE_SIZE + 1],
+            message: [0u8; MAX_DIAGNOSTIC_MESSAGE_SIZE],
+            native_error,
+            message_length: 0,
+            message_string: message,
+            row_number: None,
+            column_number: None,
+        };
+        for (raw, &unit) in rec.state[..ffi::SQL_SQLSTATE_SIZE].iter_mut().zip(state) {
+            *raw = match unit {
+                1..=0x7f => unit as u8,
+                _ => b'?',
+            };
+        }
+        let length = rec.message_string.len().min(MAX_DIAGNOSTIC_MESSAGE_SIZE - 1);
+        rec.message[..length].copy_from_slice(&rec.message_string.as_bytes()[..length]);
+        rec.message_length = length as ffi::SQLSMALLINT;
+        rec
+    }
     /// constructs an empty diagnostics message.
     /// this is needed for errors where the driver doesn't return any diagnostics info.
     pub fn empty() -> DiagnosticRecord {
@@ -43,6 +109,25 @@ impl DiagnosticRecord {
             native_error: -1,
             message_length: message.len() as ffi::SQLSMALLINT,
             message_string: String::from(""),
+            row_number: None,
+            column_number: None,
+        };
+        rec.message[..message.len()].copy_from_slice(message);
+        rec
+    }
+    /// constructs the record reported if a function returns `SQL_STILL_EXECUTING` to a caller
+    /// which does not poll, i.e. a blocking function was used while asynchronous execution is
+    /// enabled.
+    pub(crate) fn still_executing() -> DiagnosticRecord {
+        let message = b"Function is still executing asynchronously. Use the polling API instead.";
+        let mut rec = DiagnosticRecord {
+            state: *b"HY010\0",
+            message: [0u8; MAX_DIAGNOSTIC_MESSAGE_SIZE],
+            native_error: -1,
+            message_length: message.len() as ffi::SQLSMALLINT,
+            message_string: String::from_utf8_lossy(message).into_owned(),
+            row_number: None,
+            column_number: None,
         };
         rec.message[..message.len()].copy_from_slice(message);
         rec
@@ -79,13 +164,114 @@ impl Error for DiagnosticRecord {
     }
 }
 
+/// Header fields of the diagnostic data of the last call on a handle, see `SQLGetDiagField`
+///
+/// Fields which are only defined for statements are `None` for other handles, or if the driver
+/// does not report them for the last call.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DiagnosticHeader {
+    /// Number of diagnostic records (`SQL_DIAG_NUMBER`)
+    pub number: i32,
+    /// Number of rows affected by an `INSERT`, `UPDATE` or `DELETE` (`SQL_DIAG_ROW_COUNT`)
+    pub row_count: Option<i64>,
+    /// Number of rows in the cursor of a query (`SQL_DIAG_CURSOR_ROW_COUNT`)
+    pub cursor_row_count: Option<i64>,
+    /// The kind of SQL statement executed, e.g. `INSERT INTO` (`SQL_DIAG_DYNAMIC_FUNCTION`)
+    pub dynamic_function: Option<String>,
+    /// Code of the statement kind, e.g. `SQL_DIAG_INSERT` (`SQL_DIAG_DYNAMIC_FUNCTION_CODE`)
+    pub dynamic_function_code: Option<i32>,
+}
+
+impl DiagnosticHeader {
+    pub(crate) fn read(
+        handle_type: ffi::HandleType,
+        handle: ffi::SQLHANDLE,
+        encoding: &'static Encoding,
+    ) -> DiagnosticHeader {
+        let integer =
+            |identifier| diag_field::<ffi::SQLINTEGER>(handle_type, handle, 0, identifier);
+        let length = |identifier| diag_field::<ffi::SQLLEN>(handle_type, handle, 0, identifier);
+        let function = crate::SQL_DIAG_DYNAMIC_FUNCTION as i32;
+        DiagnosticHeader {
+            number: integer(crate::SQL_DIAG_NUMBER as i32).unwrap_or(0),
+            row_count: length(crate::SQL_DIAG_ROW_COUNT as i32),
+            cursor_row_count: length(crate::SQL_DIAG_CURSOR_ROW_COUNT),
+            dynamic_function: diag_field_string(handle_type, handle, 0, function, encoding)
+                .filter(|function| !function.is_empty()),
+            dynamic_function_code: integer(crate::SQL_DIAG_DYNAMIC_FUNCTION_CODE as i32),
+        }
+    }
+}
+
+/// Reads a numeric diagnostic field. Header fields have the record number `0`.
+fn diag_field<T: Copy + Default>(
+    handle_type: ffi::HandleType,
+    handle: ffi::SQLHANDLE,
+    record_number: i16,
+    identifier: i32,
+) -> Option<T> {
+    let mut value = T::default();
+    let ret = unsafe {
+        crate::SQLGetDiagField(
+            handle_type as crate::SQLSMALLINT,
+            ffi::cli_handle(handle),
+            record_number,
+            identifier as crate::SQLSMALLINT,
+            &mut value as *mut T as crate::SQLPOINTER,
+            0,
+            ptr::null_mut(),
+        )
+    };
+    match Return::from_cli("SQLGetDiagField", ret, value) {
+        Return::Success(value) | Return::SuccessWithInfo(value) => Some(value),
+        _ => None,
+    }
+}
+
+/// Reads a character diagnostic field, decoded with the OS encoding
+fn diag_field_string(
+    handle_type: ffi::HandleType,
+    handle: ffi::SQLHANDLE,
+    record_number: i16,
+    identifier: i32,
+    encoding: &'static Encoding,
+) -> Option<String> {
+    let mut buffer: Vec<u8> = vec![0; 128];
+    loop {
+        let mut length: ffi::SQLSMALLINT = 0;
+        let ret = unsafe {
+            crate::SQLGetDiagField(
+                handle_type as crate::SQLSMALLINT,
+                ffi::cli_handle(handle),
+                record_number,
+                identifier as crate::SQLSMALLINT,
+                buffer.as_mut_ptr() as crate::SQLPOINTER,
+                buffer.len() as crate::SQLSMALLINT,
+                &mut length,
+            )
+        };
+        match Return::from_cli("SQLGetDiagField", ret, ()) {
+            Return::Success(()) | Return::SuccessWithInfo(()) => (),
+            _ => return None,
+        }
+        let length = length.max(0) as usize;
+        if length < buffer.len() {
+            return Some(encoding.decode(&buffer[..length]).0.into_owned());
+        }
+        // Truncated, retry with room for the terminating null
+        buffer.resize(length + 1, 0);
+    }
+}
+
 /// Allows retrieving a diagnostic record, describing errors (or lack thereof) during the last
 /// operation.
 pub trait GetDiagRec {
     /// Retrieves a diagnostic record
     ///
-    /// `record_number` - Record numbers start at one. If you pass an number < 1 the function will
-    /// panic. If no record is available for the number specified none is returned.
+    /// `record_number` - Record numbers start at one. If you pass an number < 1 the error is
+    /// logged and none is returned, just like if no record is available for the number specified.
+    /// The message is decoded as UTF-8, the errors returned by this crate decode it with the OS
+    /// encoding of the statement or connection instead.
     fn get_diag_rec(&self, record_number: i16) -> Option<DiagnosticRecord>;
 }
 
@@ -94,30 +280,122 @@ where
     D: safe::Diagnostics,
 {
     fn get_diag_rec(&self, record_number: i16) -> Option<DiagnosticRecord> {
-        use safe::ReturnOption::*;
-        let mut message = [0; MAX_DIAGNOSTIC_MESSAGE_SIZE];
-        match self.diagnostics(record_number, &mut message) {
-            Success(result) | Info(result) => {
-                // The message could be larger than the supplied buffer, so we need to limit the message length to the buffer size.
-                let mut message_length = cmp::min(result.text_length, MAX_DIAGNOSTIC_MESSAGE_SIZE as ffi::SQLSMALLINT - 1);
-                // Some drivers pad the message with null-chars (which is still a valid C string, but not a valid Rust string).
-                while message_length > 0 && message[(message_length - 1) as usize] == 0 {
-                    message_length -= 1;
+        read_diag_rec(self, record_number, encoding_rs::UTF_8)
+    }
+}
+
+/// Reads a diagnostic record, decoding its message with `encoding`
+pub(crate) fn read_diag_rec<D: safe::Diagnostics + ?Sized>(
+    diagnostics: &D,
+    record_number: i16,
+    encoding: &'static Encoding,
+) -> Option<DiagnosticRecord> {
+    use safe::ReturnOption::*;
+    let mut message = [0; MAX_DIAGNOSTIC_MESSAGE_SIZE];
+    match diagnostics.diagnostics(record_number, &mut message) {
+        Success(result) | Info(result) => {
+            // The message could be larger than the supplied buffer, so we need to limit the message length to the buffer size.
+            let mut message_length = cmp::min(
+                result.text_length,
+                MAX_DIAGNOSTIC_MESSAGE_SIZE as ffi::SQLSMALLINT - 1,
+            );
+            // Some drivers pad the message with null-chars (which is still a valid C string, but not a valid Rust string).
+            while message_length > 0 && message[(message_length - 1) as usize] == 0 {
+                message_length -= 1;
+            }
+            // Read the message again with a large enough buffer to keep it complete
+            let full_message = if result.text_length as usize >= MAX_DIAGNOSTIC_MESSAGE_SIZE {
+                let mut full_message = vec![0; result.text_length as usize + 1];
+                match diagnostics.diagnostics(record_number, &mut full_message) {
+                    Success(full) | Info(full) if full.text_length == result.text_length => {
+                        full_message.truncate(full.text_length as usize);
+                        while full_message.last() == Some(&0) {
+                            full_message.pop();
+                        }
+                        Some(full_message)
+                    }
+                    _ => None,
                 }
-                Some(DiagnosticRecord {
-                    state: result.state,
-                    native_error: result.native_error,
-                    message_length,
-                    message,
-                    message_string: unsafe {
-                        super::environment::OS_ENCODING.decode(&message[0..message_length as usize]).0.into_owned()
-                    },
-                })
+            } else {
+                None
+            };
+            let truncated = &message[0..message_length as usize];
+            let decoded = full_message.as_deref().unwrap_or(truncated);
+            Some(DiagnosticRecord {
+                state: result.state,
+                native_error: result.native_error,
+                message_length,
+                message,
+                message_string: encoding.decode(decoded).0.into_owned(),
+                row_number: None,
+                column_number: None,
+            })
+        }
+        NoData(()) => None,
+        Error(()) => {
+            error!(
+                "Diagnostics returned error for record number {}. Record numbers have to be at \
+                 least 1.",
+                record_number
+            );
+            None
+        }
+    }
+}
+
+/// Like `read_diag_rec`, but reads the record with `SQLGetDiagRecW`. The raw message of the record
+/// holds the UTF-8 encoded text.
+pub(crate) fn read_diag_rec_wide(
+    handle_type: ffi::HandleType,
+    handle: ffi::SQLHANDLE,
+    record_number: i16,
+) -> Option<DiagnosticRecord> {
+    let mut state = [0u16; ffi::SQL_SQLSTATE_SIZE + 1];
+    let mut native_error: ffi::SQLINTEGER = 0;
+    let mut message = vec![0u16; MAX_DIAGNOSTIC_MESSAGE_SIZE];
+    loop {
+        let mut length: ffi::SQLSMALLINT = 0;
+        let ret = unsafe {
+            ffi::SQLGetDiagRecW(
+                handle_type,
+                handle,
+                record_number,
+                state.as_mut_ptr(),
+                &mut native_error,
+                message.as_mut_ptr(),
+                message.len() as ffi::SQLSMALLINT,
+                &mut length,
+            )
+        };
+        match ret {
+            ffi::SQL_SUCCESS | ffi::SQL_SUCCESS_WITH_INFO => (),
+            ffi::SQL_NO_DATA => return None,
+            _ => {
+                error!(
+                    "Diagnostics returned error for record number {}. Record numbers have to be \
+                     at least 1.",
+                    record_number
+                );
+                return None;
             }
-            NoData(()) => None,
-            Error(()) => panic!("Diagnostics returned error for record number {}. Record numbers have to be at least 1.", record_number),
         }
+        let length = length.max(0) as usize;
+        if length < message.len() {
+            message.truncate(length);
+            break;
+        }
+        // Truncated, retry with room for the terminating null
+        message.resize(length + 1, 0);
+    }
+    // Some drivers pad the message with null-chars
+    while message.last() == Some(&0) {
+        message.pop();
     }
+    Some(DiagnosticRecord::from_text(
+        &state[..ffi::SQL_SQLSTATE_SIZE],
+        native_error,
+        from_wide(&message),
+    ))
 }
 
 #[cfg(test)]
@@ -133,6 +411,8 @@ mod test {
                 native_error: 0,
                 message_length: 0,
                 message_string: String::from(""),
+                row_number: None,
+                column_number: None,
             }
         }
     }
@@ -160,4 +440,18 @@ mod test {
              Function sequence error"
         );
     }
+
+    #[test]
+    fn from_text() {
+        let state: Vec<u16> = "42S02".encode_utf16().collect();
+        let message = "SQL0204N  \"DB2INST1.TÄBLE\" is an undefined name.".to_owned();
+        let rec = DiagnosticRecord::from_text(&state, -204, message.clone());
+        assert_eq!(rec.get_raw_state(), b"42S02\0");
+        assert_eq!(rec.get_message(), message);
+        assert_eq!(rec.get_raw_message(), message.as_bytes());
+        assert_eq!(
+            format!("{}", rec),
+            format!("State: 42S02, Native error: -204, Message: {}", message)
+        );
+    }
 }
@@ -0,0 +1,888 @@
+//! Reading and writing of the `db2dsdriver.cfg` client configuration file
+//!
+//! The file lives in the `cfg` folder of the CLI driver, i.e. `$IBM_DB_HOME/cfg`, and defines
+//! data source names (DSNs), database connection settings and global parameters:
+//!
+//! ```xml
+//! <configuration>
+//!   <dsncollection>
+//!     <dsn alias="sample" name="SAMPLE" host="db.example.com" port="50000"/>
+//!   </dsncollection>
+//!   <databases>
+//!     <database name="SAMPLE" host="db.example.com" port="50000">
+//!       <parameter name="CurrentSchema" value="APP"/>
+//!     </database>
+//!   </databases>
+//! </configuration>
+//! ```
+//!
+//! Sections without a typed representation, like `acr`, `wlb` or `ldapserver`, are kept as
+//! generic `Element`s, so a file can be read and written back without losing settings. XML
+//! comments and text content are not kept however, so saving a loaded file drops its comments.
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A `<parameter name="..." value="..."/>` entry
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub value: String,
+}
+
+/// A data source name defined in the `<dsncollection>` section
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Dsn {
+    /// Name used as `DSN` in connection strings
+    pub alias: String,
+    /// Name of the database on the server
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub parameters: Vec<Parameter>,
+    /// Child elements other than `<parameter>`
+    pub elements: Vec<Element>,
+}
+
+impl Dsn {
+    pub fn new<A, N, H>(alias: A, name: N, host: H, port: u16) -> Dsn
+    where
+        A: Into<String>,
+        N: Into<String>,
+        H: Into<String>,
+    {
+        Dsn {
+            alias: alias.into(),
+            name: name.into(),
+            host: host.into(),
+            port,
+            parameters: Vec::new(),
+            elements: Vec::new(),
+        }
+    }
+
+    /// Adds a parameter, replacing a previous value of the same parameter
+    pub fn parameter<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Dsn {
+        set_parameter(&mut self.parameters, name.into(), value.into());
+        self
+    }
+}
+
+/// Connection settings of a database defined in the `<databases>` section
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Database {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub parameters: Vec<Parameter>,
+    /// Child elements other than `<parameter>`, e.g. `<acr>` or `<wlb>`
+    pub elements: Vec<Element>,
+}
+
+impl Database {
+    pub fn new<N: Into<String>, H: Into<String>>(name: N, host: H, port: u16) -> Database {
+        Database {
+            name: name.into(),
+            host: host.into(),
+            port,
+            parameters: Vec::new(),
+            elements: Vec::new(),
+        }
+    }
+
+    /// Adds a parameter, replacing a previous value of the same parameter
+    pub fn parameter<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Database {
+        set_parameter(&mut self.parameters, name.into(), value.into());
+        self
+    }
+}
+
+/// An XML element without typed representation
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Contents of a `db2dsdriver.cfg` file
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DsDriverConfig {
+    pub dsns: Vec<Dsn>,
+    /// Children of `<dsncollection>` other than `<dsn>`
+    pub dsncollection_elements: Vec<Element>,
+    pub databases: Vec<Database>,
+    /// Children of `<databases>` other than `<database>`
+    pub databases_elements: Vec<Element>,
+    /// Global parameters of the `<parameters>` section
+    pub parameters: Vec<Parameter>,
+    /// Children of `<parameters>` other than `<parameter>`
+    pub parameters_elements: Vec<Element>,
+    /// Sections other than `<dsncollection>`, `<databases>` and `<parameters>`
+    pub elements: Vec<Element>,
+}
+
+impl DsDriverConfig {
+    /// Creates an empty configuration
+    pub fn new() -> DsDriverConfig {
+        DsDriverConfig::default()
+    }
+
+    /// Location of the configuration file of the CLI driver in `IBM_DB_HOME`, if the variable
+    /// is set
+    pub fn default_path() -> Option<PathBuf> {
+        std::env::var_os("IBM_DB_HOME")
+            .map(|home| Path::new(&home).join("cfg").join("db2dsdriver.cfg"))
+    }
+
+    /// Reads and parses a configuration file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<DsDriverConfig, ConfigError> {
+        let xml = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        DsDriverConfig::parse(&xml)
+    }
+
+    /// Validates the configuration and writes it to a file. Comments of a loaded file are not
+    /// written back, see the module documentation.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        self.validate()?;
+        fs::write(path, self.to_xml()).map_err(ConfigError::Io)
+    }
+
+    /// Parses the contents of a configuration file
+    pub fn parse(xml: &str) -> Result<DsDriverConfig, ConfigError> {
+        let root = XmlParser::new(xml).parse_document()?;
+        if root.name != "configuration" {
+            return Err(ConfigError::Invalid(vec![format!(
+                "expected <configuration> as root element, found <{}>",
+                root.name
+            )]));
+        }
+        let mut config = DsDriverConfig::new();
+        for section in root.children {
+            match section.name.as_str() {
+                "dsncollection" => {
+                    for element in section.children {
+                        if element.name == "dsn" {
+                            config.dsns.push(dsn_from_element(element)?);
+                        } else {
+                            config.dsncollection_elements.push(element);
+                        }
+                    }
+                }
+                "databases" => {
+                    for element in section.children {
+                        if element.name == "database" {
+                            config.databases.push(database_from_element(element)?);
+                        } else {
+                            config.databases_elements.push(element);
+                        }
+                    }
+                }
+                "parameters" => {
+                    let (parameters, elements) = split_parameters(section.children)?;
+                    config.parameters = parameters;
+                    config.parameters_elements = elements;
+                }
+                _ => config.elements.push(section),
+            }
+        }
+        Ok(config)
+    }
+
+    /// The DSN with the given alias, compared case insensitively
+    pub fn dsn(&self, alias: &str) -> Option<&Dsn> {
+        self.dsns
+            .iter()
+            .find(|d| d.alias.eq_ignore_ascii_case(alias))
+    }
+
+    /// Adds a DSN, replacing a DSN with the same alias
+    pub fn set_dsn(&mut self, dsn: Dsn) {
+        match self
+            .dsns
+            .iter_mut()
+            .find(|d| d.alias.eq_ignore_ascii_case(&dsn.alias))
+        {
+            Some(existing) => *existing = dsn,
+            None => self.dsns.push(dsn),
+        }
+    }
+
+    /// Removes the DSN with the given alias. Returns it, if it existed.
+    pub fn remove_dsn(&mut self, alias: &str) -> Option<Dsn> {
+        let index = self
+            .dsns
+            .iter()
+            .position(|d| d.alias.eq_ignore_ascii_case(alias))?;
+        Some(self.dsns.remove(index))
+    }
+
+    /// The database entry for the given database name, host and port
+    pub fn database(&self, name: &str, host: &str, port: u16) -> Option<&Database> {
+        self.databases
+            .iter()
+            .find(|d| is_same_database(d, name, host, port))
+    }
+
+    /// Adds a database entry, replacing an entry for the same database name, host and port
+    pub fn set_database(&mut self, database: Database) {
+        match self
+            .databases
+            .iter_mut()
+            .find(|d| is_same_database(d, &database.name, &database.host, database.port))
+        {
+            Some(existing) => *existing = database,
+            None => self.databases.push(database),
+        }
+    }
+
+    /// Checks for problems DB2 would either reject or silently ignore: empty names, port `0`,
+    /// duplicate DSN aliases, duplicate database entries and duplicate parameters.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        for (i, dsn) in self.dsns.iter().enumerate() {
+            if dsn.alias.is_empty() {
+                problems.push(format!("DSN #{} has an empty alias", i + 1));
+            }
+            if dsn.name.is_empty() {
+                problems.push(format!("DSN {} has an empty database name", dsn.alias));
+            }
+            if dsn.host.is_empty() {
+                problems.push(format!("DSN {} has an empty host", dsn.alias));
+            }
+            if dsn.port == 0 {
+                problems.push(format!("DSN {} has port 0", dsn.alias));
+            }
+            if self.dsns[..i]
+                .iter()
+                .any(|d| d.alias.eq_ignore_ascii_case(&dsn.alias))
+            {
+                problems.push(format!("DSN {} is defined more than once", dsn.alias));
+            }
+            check_parameters(
+                &dsn.parameters,
+                &format!("DSN {}", dsn.alias),
+                &mut problems,
+            );
+        }
+        for (i, database) in self.databases.iter().enumerate() {
+            let description = format!(
+                "Database {} on {}:{}",
+                database.name, database.host, database.port
+            );
+            if database.name.is_empty() {
+                problems.push(format!("Database #{} has an empty name", i + 1));
+            }
+            if database.host.is_empty() {
+                problems.push(format!("{} has an empty host", description));
+            }
+            if database.port == 0 {
+                problems.push(format!("{} has port 0", description));
+            }
+            if self.databases[..i]
+                .iter()
+                .any(|d| is_same_database(d, &database.name, &database.host, database.port))
+            {
+                problems.push(format!("{} is defined more than once", description));
+            }
+            check_parameters(&database.parameters, &description, &mut problems);
+        }
+        check_parameters(&self.parameters, "Global parameters", &mut problems);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// Serializes the configuration into the XML format of `db2dsdriver.cfg`
+    pub fn to_xml(&self) -> String {
+        let mut out =
+            String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\" ?>\n");
+        let mut root = Element {
+            name: "configuration".to_string(),
+            ..Element::default()
+        };
+        push_section(
+            &mut root,
+            "dsncollection",
+            self.dsns.iter().map(dsn_to_element),
+            &self.dsncollection_elements,
+        );
+        push_section(
+            &mut root,
+            "databases",
+            self.databases.iter().map(database_to_element),
+            &self.databases_elements,
+        );
+        push_section(
+            &mut root,
+            "parameters",
+            self.parameters.iter().map(parameter_to_element),
+            &self.parameters_elements,
+        );
+        root.children.extend(self.elements.iter().cloned());
+        write_element(&root, 0, &mut out);
+        out
+    }
+}
+
+/// Makes the CLI driver reload the client affinity settings (`DSD_ACR_AFFINITY`) of
+/// `db2dsdriver.cfg` without restarting the application. DB2 does not support reloading other
+/// settings, new DSNs are picked up by new environments.
+pub fn reload_acr_affinity() -> Result<(), ConfigError> {
+    let mut buffer = [0u8; 1024];
+    let mut length: crate::SQLSMALLINT = 0;
+    let ret = unsafe {
+        crate::SQLReloadConfig(
+            crate::DSD_ACR_AFFINITY as crate::SQLINTEGER,
+            buffer.as_mut_ptr(),
+            buffer.len() as crate::SQLSMALLINT,
+            &mut length,
+        )
+    };
+    let length = (length.max(0) as usize).min(buffer.len());
+    // Not tied to an environment, so decoded as UTF-8 like the records of `get_diag_rec`
+    let message = encoding_rs::UTF_8.decode(&buffer[..length]).0.into_owned();
+    match ret as u32 {
+        crate::SQL_SUCCESS => Ok(()),
+        crate::SQL_SUCCESS_WITH_INFO => {
+            warn!("{}", message);
+            Ok(())
+        }
+        _ => Err(ConfigError::Reload(message)),
+    }
+}
+
+/// Errors reading, validating, writing or reloading `db2dsdriver.cfg`
+#[derive(Debug)]
+pub enum ConfigError {
+    /// Reading or writing the file failed
+    Io(io::Error),
+    /// The file is not well formed XML. `position` is the byte offset of the error.
+    Parse { position: usize, message: String },
+    /// The configuration is well formed, but contains the listed problems
+    Invalid(Vec<String>),
+    /// `SQLReloadConfig` failed with the given message
+    Reload(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "Error accessing db2dsdriver.cfg: {}", e),
+            ConfigError::Parse { position, message } => write!(
+                f,
+                "Malformed db2dsdriver.cfg at byte {}: {}",
+                position, message
+            ),
+            ConfigError::Invalid(problems) => {
+                write!(f, "Invalid db2dsdriver.cfg: {}", problems.join("; "))
+            }
+            ConfigError::Reload(message) => {
+                write!(f, "Error reloading db2dsdriver.cfg: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+fn set_parameter(parameters: &mut Vec<Parameter>, name: String, value: String) {
+    match parameters
+        .iter_mut()
+        .find(|p| p.name.eq_ignore_ascii_case(&name))
+    {
+        Some(parameter) => parameter.value = value,
+        None => parameters.push(Parameter { name, value }),
+    }
+}
+
+fn check_parameters(parameters: &[Parameter], owner: &str, problems: &mut Vec<String>) {
+    for (i, parameter) in parameters.iter().enumerate() {
+        if parameter.name.is_empty() {
+            problems.push(format!("{} contains a parameter without name", owner));
+        } else if parameters[..i]
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(&parameter.name))
+        {
+            problems.push(format!(
+                "{} sets parameter {} more than once",
+                owner, parameter.name
+            ));
+        }
+    }
+}
+
+fn is_same_database(database: &Database, name: &str, host: &str, port: u16) -> bool {
+    database.name.eq_ignore_ascii_case(name)
+        && database.host.eq_ignore_ascii_case(host)
+        && database.port == port
+}
+
+fn required_attribute(element: &Element, name: &str) -> Result<String, ConfigError> {
+    element.attribute(name).map(str::to_string).ok_or_else(|| {
+        ConfigError::Invalid(vec![format!(
+            "<{}> is missing the {} attribute",
+            element.name, name
+        )])
+    })
+}
+
+fn port_attribute(element: &Element) -> Result<u16, ConfigError> {
+    let port = required_attribute(element, "port")?;
+    port.trim().parse().map_err(|_| {
+        ConfigError::Invalid(vec![format!(
+            "<{}> has an invalid port: {}",
+            element.name, port
+        )])
+    })
+}
+
+/// Separates `<parameter>` children from other elements
+fn split_parameters(children: Vec<Element>) -> Result<(Vec<Parameter>, Vec<Element>), ConfigError> {
+    let mut parameters = Vec::new();
+    let mut elements = Vec::new();
+    for child in children {
+        if child.name == "parameter" {
+            parameters.push(Parameter {
+                name: required_attribute(&child, "name")?,
+                value: required_attribute(&child, "value")?,
+            });
+        } else {
+            elements.push(child);
+        }
+    }
+    Ok((parameters, elements))
+}
+
+fn dsn_from_element(element: Element) -> Result<Dsn, ConfigError> {
+    let alias = required_attribute(&element, "alias")?;
+    let name = required_attribute(&element, "name")?;
+    let host = required_attribute(&element, "host")?;
+    let port = port_attribute(&element)?;
+    let (parameters, elements) = split_parameters(element.children)?;
+    Ok(Dsn {
+        alias,
+        name,
+        host,
+        port,
+        parameters,
+        elements,
+    })
+}
+
+fn database_from_element(element: Element) -> Result<Database, ConfigError> {
+    let name = required_attribute(&element, "name")?;
+    let host = required_attribute(&element, "host")?;
+    let port = port_attribute(&element)?;
+    let (parameters, elements) = split_parameters(element.children)?;
+    Ok(Database {
+        name,
+        host,
+        port,
+        parameters,
+        elements,
+    })
+}
+
+/// Adds a section with the typed entries followed by the other `elements`, unless both are empty
+fn push_section<I>(root: &mut Element, name: &str, entries: I, elements: &[Element])
+where
+    I: Iterator<Item = Element>,
+{
+    let mut children: Vec<Element> = entries.collect();
+    children.extend(elements.iter().cloned());
+    if !children.is_empty() {
+        root.children.push(Element {
+            name: name.to_string(),
+            attributes: Vec::new(),
+            children,
+        });
+    }
+}
+
+fn parameter_to_element(parameter: &Parameter) -> Element {
+    Element {
+        name: "parameter".to_string(),
+        attributes: vec![
+            ("name".to_string(), parameter.name.clone()),
+            ("value".to_string(), parameter.value.clone()),
+        ],
+        children: Vec::new(),
+    }
+}
+
+fn dsn_to_element(dsn: &Dsn) -> Element {
+    let mut children: Vec<Element> = dsn.parameters.iter().map(parameter_to_element).collect();
+    children.extend(dsn.elements.iter().cloned());
+    Element {
+        name: "dsn".to_string(),
+        attributes: vec![
+            ("alias".to_string(), dsn.alias.clone()),
+            ("name".to_string(), dsn.name.clone()),
+            ("host".to_string(), dsn.host.clone()),
+            ("port".to_string(), dsn.port.to_string()),
+        ],
+        children,
+    }
+}
+
+fn database_to_element(database: &Database) -> Element {
+    let mut children: Vec<Element> = database
+        .parameters
+        .iter()
+        .map(parameter_to_element)
+        .collect();
+    children.extend(database.elements.iter().cloned());
+    Element {
+        name: "database".to_string(),
+        attributes: vec![
+            ("name".to_string(), database.name.clone()),
+            ("host".to_string(), database.host.clone()),
+            ("port".to_string(), database.port.to_string()),
+        ],
+        children,
+    }
+}
+
+fn write_element(element: &Element, depth: usize, out: &mut String) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+    out.push('<');
+    out.push_str(&element.name);
+    for (name, value) in &element.attributes {
+        out.push(' ');
+        out.push_str(name);
+        out.push_str("=\"");
+        out.push_str(&escape(value));
+        out.push('"');
+    }
+    if element.children.is_empty() {
+        out.push_str("/>\n");
+        return;
+    }
+    out.push_str(">\n");
+    for child in &element.children {
+        write_element(child, depth + 1, out);
+    }
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+    out.push_str("</");
+    out.push_str(&element.name);
+    out.push_str(">\n");
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Minimal XML parser covering what appears in `db2dsdriver.cfg`: elements, attributes,
+/// comments, processing instructions and the predefined entities. Text content is ignored.
+struct XmlParser<'a> {
+    xml: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    fn new(xml: &'a str) -> XmlParser<'a> {
+        XmlParser { xml, pos: 0 }
+    }
+
+    fn parse_document(&mut self) -> Result<Element, ConfigError> {
+        self.skip_misc()?;
+        let root = self.parse_element()?;
+        self.skip_misc()?;
+        if self.pos < self.xml.len() {
+            return Err(self.error("unexpected content after root element"));
+        }
+        Ok(root)
+    }
+
+    /// Skips whitespace, comments, processing instructions and the document type declaration
+    fn skip_misc(&mut self) -> Result<(), ConfigError> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_element(&mut self) -> Result<Element, ConfigError> {
+        self.expect("<")?;
+        let mut element = Element {
+            name: self.parse_name()?,
+            ..Element::default()
+        };
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let name = self.parse_name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let value = self.parse_attribute_value()?;
+            element.attributes.push((name, value));
+        }
+        loop {
+            // Text content carries no information in db2dsdriver.cfg
+            match self.rest().find('<') {
+                Some(offset) => self.pos += offset,
+                None => return Err(self.error(&format!("unclosed element <{}>", element.name))),
+            }
+            if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("</") {
+                self.pos += 2;
+                let name = self.parse_name()?;
+                if name != element.name {
+                    return Err(
+                        self.error(&format!("expected </{}>, found </{}>", element.name, name))
+                    );
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(element);
+            } else {
+                element.children.push(self.parse_element()?);
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, ConfigError> {
+        let length = self
+            .rest()
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/' || c == '>' || c == '<')
+            .unwrap_or_else(|| self.rest().len());
+        if length == 0 {
+            return Err(self.error("expected a name"));
+        }
+        let name = self.rest()[..length].to_string();
+        self.pos += length;
+        Ok(name)
+    }
+
+    fn parse_attribute_value(&mut self) -> Result<String, ConfigError> {
+        let quote = match self.rest().chars().next() {
+            Some(c) if c == '"' || c == '\'' => c,
+            _ => return Err(self.error("expected a quoted attribute value")),
+        };
+        self.pos += 1;
+        let length = match self.rest().find(quote) {
+            Some(length) => length,
+            None => return Err(self.error("unterminated attribute value")),
+        };
+        let value = unescape(&self.rest()[..length]).map_err(|message| self.error(message))?;
+        self.pos += length + 1;
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<(), ConfigError> {
+        match self.rest().find(end) {
+            Some(offset) => {
+                self.pos += offset + end.len();
+                Ok(())
+            }
+            None => Err(self.error(&format!("missing {}", end))),
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ConfigError> {
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", token)))
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.xml[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> ConfigError {
+        ConfigError::Parse {
+            position: self.pos,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn unescape(value: &str) -> Result<String, &'static str> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(';').ok_or("unterminated entity")?;
+        let entity = &rest[1..end];
+        match entity {
+            "amp" => result.push('&'),
+            "lt" => result.push('<'),
+            "gt" => result.push('>'),
+            "quot" => result.push('"'),
+            "apos" => result.push('\''),
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(decimal) = entity.strip_prefix('#') {
+                    decimal.parse().ok()
+                } else {
+                    None
+                };
+                match code.and_then(std::char::from_u32) {
+                    Some(c) => result.push(c),
+                    None => return Err("unknown entity"),
+                }
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const EXAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no" ?>
+<configuration>
+  <!-- comment -->
+  <dsncollection>
+	<dsn alias="dashdb4" host="test@test.com" name="FOO" port="0000"/>
+	</dsncollection>
+
+  <databases>
+	<database host="test@test.com" name="FOO" port="50001">
+	  <parameter name="SecurityTransportMode" value="SSL"/>
+	  <acr><parameter name="enableAcr" value="true"/></acr>
+	</database>
+	</databases>
+
+</configuration>
+"#;
+
+    #[test]
+    fn parse_example() {
+        let config = DsDriverConfig::parse(EXAMPLE).unwrap();
+        let dsn = config.dsn("DASHDB4").unwrap();
+        assert_eq!(dsn.name, "FOO");
+        assert_eq!(dsn.port, 0);
+        let database = config.database("FOO", "test@test.com", 50001).unwrap();
+        assert_eq!(database.parameters[0].value, "SSL");
+        assert_eq!(database.elements[0].name, "acr");
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut config = DsDriverConfig::parse(EXAMPLE).unwrap();
+        config.set_dsn(
+            Dsn::new("sample", "SAMPLE", "db.example.com", 50000)
+                .parameter("CurrentSchema", "A&\"B\""),
+        );
+        let xml = config.to_xml();
+        assert_eq!(DsDriverConfig::parse(&xml).unwrap(), config);
+    }
+
+    #[test]
+    fn round_trip_unknown_elements() {
+        let xml = r#"<configuration>
+  <dsncollection>
+    <dsn alias="sample" name="SAMPLE" host="localhost" port="50000">
+      <sessionglobalvariables/>
+    </dsn>
+    <dsngroup name="all"/>
+  </dsncollection>
+  <databases>
+    <database name="SAMPLE" host="localhost" port="50000"/>
+    <databasegroup name="all"/>
+  </databases>
+  <parameters>
+    <parameter name="CommProtocol" value="TCPIP"/>
+    <specialregisters><parameter name="CURRENT DEGREE" value="ANY"/></specialregisters>
+  </parameters>
+  <ldapserver host="ldap.example.com" port="389"/>
+</configuration>
+"#;
+        let config = DsDriverConfig::parse(xml).unwrap();
+        assert_eq!(config.dsns[0].elements[0].name, "sessionglobalvariables");
+        assert_eq!(config.dsncollection_elements[0].name, "dsngroup");
+        assert_eq!(config.databases_elements[0].name, "databasegroup");
+        assert_eq!(config.parameters[0].name, "CommProtocol");
+        assert_eq!(config.parameters_elements[0].name, "specialregisters");
+        assert_eq!(config.elements[0].name, "ldapserver");
+        assert_eq!(DsDriverConfig::parse(&config.to_xml()).unwrap(), config);
+    }
+
+    #[test]
+    fn validate() {
+        let mut config = DsDriverConfig::parse(EXAMPLE).unwrap();
+        config
+            .dsns
+            .push(Dsn::new("DASHDB4", "BAR", "localhost", 50000));
+        match config.validate() {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(problems.len(), 2),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(DsDriverConfig::parse("<configuration>").is_err());
+        assert!(DsDriverConfig::parse("<configuration></databases>").is_err());
+        assert!(DsDriverConfig::parse("<databases/>").is_err());
+    }
+}
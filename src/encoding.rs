@@ -0,0 +1,265 @@
+//! Character encodings used to exchange text with the CLI
+use encoding_rs::Encoding;
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt;
+
+/// The character encodings text is exchanged with the CLI in
+///
+/// `os` is used for the text generated by the client, i.e. the messages of diagnostic records and
+/// the tokens of the SQLCA. `db` is used for statement text, object names and character data.
+/// Both default to UTF-8.
+///
+/// Connections start with the encoding of the environment they are created in. It can be changed
+/// per connection, e.g. if the databases use different code pages. Statements use the encoding of
+/// their connection at the time they are allocated. `Connection::detect_encoding` and
+/// `Environment::set_detect_encoding` take it from the code page reported by the client instead.
+///
+/// ```no_run
+/// # use ibm_db::{create_environment_v3, TextEncoding};
+/// # fn connect() -> ibm_db::Result<()> {
+/// let env = create_environment_v3().unwrap();
+/// let mut conn = env.connect_with_connection_string("DSN=sjisdb")?;
+/// conn.set_encoding(TextEncoding::from_labels("utf-8", "shift_jis")?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// With `with_wide_api` text is exchanged as UTF-16 through the wide (`W`) functions instead, so it
+/// does not depend on the client code page at all.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TextEncoding {
+    os: &'static Encoding,
+    db: &'static Encoding,
+    wide: bool,
+}
+
+impl Default for TextEncoding {
+    fn default() -> TextEncoding {
+        TextEncoding::new(encoding_rs::UTF_8, encoding_rs::UTF_8)
+    }
+}
+
+impl TextEncoding {
+    /// Uses `os` for the text generated by the client and `db` for everything else
+    pub fn new(os: &'static Encoding, db: &'static Encoding) -> TextEncoding {
+        TextEncoding {
+            os,
+            db,
+            wide: false,
+        }
+    }
+
+    /// Looks up both encodings by their WHATWG labels, e.g. `utf-8`, `sjis` or `windows-1252`
+    pub fn from_labels(os: &str, db: &str) -> Result<TextEncoding, UnknownEncoding> {
+        Ok(TextEncoding::new(lookup(os)?, lookup(db)?))
+    }
+
+    /// Uses the encoding of the DB2 code page `ccsid` for both, see `encoding_for_ccsid`
+    pub fn from_ccsid(ccsid: u32) -> Result<TextEncoding, UnknownCcsid> {
+        let encoding = encoding_for_ccsid(ccsid)?;
+        Ok(TextEncoding::new(encoding, encoding))
+    }
+
+    /// Encoding of the text generated by the client
+    pub fn os(&self) -> &'static Encoding {
+        self.os
+    }
+
+    /// Encoding of statement text, object names and character data
+    pub fn db(&self) -> &'static Encoding {
+        self.db
+    }
+
+    /// Exchanges statement text, column and object names, character data, diagnostic messages
+    /// and string valued info types and attributes as UTF-16 through the wide functions, e.g.
+    /// `SQLExecDirectW` and `SQLGetDiagRecW`, if `wide` is `true`.
+    ///
+    /// Connecting, listing data sources and drivers, the tokens of the SQLCA and the fields of
+    /// the `DiagnosticHeader` still go through the narrow functions and use `os` and `db`.
+    pub fn with_wide_api(self, wide: bool) -> TextEncoding {
+        TextEncoding { wide, ..self }
+    }
+
+    /// Whether text is exchanged through the wide functions, see `with_wide_api`
+    pub fn is_wide(&self) -> bool {
+        self.wide
+    }
+
+    /// Encodes character data for a buffer passed to the CLI, as UTF-16 in native byte order if
+    /// the wide functions are used
+    pub(crate) fn encode_text<'t>(&self, text: &'t str) -> Cow<'t, [u8]> {
+        if self.wide {
+            Cow::Owned(text.encode_utf16().flat_map(u16::to_ne_bytes).collect())
+        } else {
+            self.db.encode(text).0
+        }
+    }
+
+    /// Decodes character data written into a buffer by the CLI, see `encode_text`
+    pub(crate) fn decode_text<'t>(&self, bytes: &'t [u8]) -> Cow<'t, str> {
+        if self.wide {
+            Cow::Owned(from_wide(&wide_units(bytes)))
+        } else {
+            self.db.decode(bytes).0
+        }
+    }
+}
+
+/// Text as UTF-16 code units, as passed to the wide functions
+pub(crate) fn to_wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().collect()
+}
+
+/// Decodes UTF-16 code units returned by the wide functions. Unpaired surrogates are replaced.
+pub(crate) fn from_wide(units: &[u16]) -> String {
+    String::from_utf16_lossy(units)
+}
+
+/// UTF-16 code units written into a byte buffer in native byte order. An odd trailing byte is
+/// ignored.
+pub(crate) fn wide_units(bytes: &[u8]) -> Vec<u16> {
+    bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_ne_bytes([unit[0], unit[1]]))
+        .collect()
+}
+
+fn lookup(label: &str) -> Result<&'static Encoding, UnknownEncoding> {
+    Encoding::for_label(label.as_bytes()).ok_or_else(|| UnknownEncoding {
+        label: label.to_owned(),
+    })
+}
+
+/// Encoding of the DB2 code page (CCSID) `ccsid`, e.g. UTF-8 for `1208` or Shift_JIS for `943`
+///
+/// Only ASCII based single and multi byte code pages are mapped. EBCDIC code pages and the UTF-16
+/// code pages `1200` and `13488` fail, as `encoding_rs` can not encode text with them.
+pub fn encoding_for_ccsid(ccsid: u32) -> Result<&'static Encoding, UnknownCcsid> {
+    let encoding = match ccsid {
+        1208 => encoding_rs::UTF_8,
+        // ISO-8859-1 and ASCII are decoded as their superset windows-1252, like WHATWG does
+        367 | 819 | 1252 | 5348 => encoding_rs::WINDOWS_1252,
+        1250 | 5346 => encoding_rs::WINDOWS_1250,
+        1251 | 5347 => encoding_rs::WINDOWS_1251,
+        1253 | 5349 => encoding_rs::WINDOWS_1253,
+        1254 | 5350 | 920 => encoding_rs::WINDOWS_1254,
+        1255 | 5351 => encoding_rs::WINDOWS_1255,
+        1256 | 5352 => encoding_rs::WINDOWS_1256,
+        1257 | 5353 => encoding_rs::WINDOWS_1257,
+        1258 | 5354 => encoding_rs::WINDOWS_1258,
+        874 | 9066 => encoding_rs::WINDOWS_874,
+        912 => encoding_rs::ISO_8859_2,
+        913 => encoding_rs::ISO_8859_3,
+        914 => encoding_rs::ISO_8859_4,
+        915 => encoding_rs::ISO_8859_5,
+        1089 => encoding_rs::ISO_8859_6,
+        813 | 4909 => encoding_rs::ISO_8859_7,
+        916 => encoding_rs::ISO_8859_8,
+        921 => encoding_rs::ISO_8859_13,
+        923 => encoding_rs::ISO_8859_15,
+        866 => encoding_rs::IBM866,
+        878 => encoding_rs::KOI8_R,
+        1168 => encoding_rs::KOI8_U,
+        932 | 943 => encoding_rs::SHIFT_JIS,
+        954 | 5050 => encoding_rs::EUC_JP,
+        1381 | 1383 | 1386 => encoding_rs::GBK,
+        1392 | 5488 => encoding_rs::GB18030,
+        970 | 1363 => encoding_rs::EUC_KR,
+        950 | 1370 => encoding_rs::BIG5,
+        _ => return Err(UnknownCcsid { ccsid }),
+    };
+    Ok(encoding)
+}
+
+/// Returned for a code page which has no mapping to an `encoding_rs` encoding
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UnknownCcsid {
+    ccsid: u32,
+}
+
+impl UnknownCcsid {
+    /// The code page which could not be mapped
+    pub fn ccsid(&self) -> u32 {
+        self.ccsid
+    }
+}
+
+impl fmt::Display for UnknownCcsid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No encoding known for CCSID {}", self.ccsid)
+    }
+}
+
+impl StdError for UnknownCcsid {}
+
+/// Returned for an encoding label which is not known to `encoding_rs`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownEncoding {
+    label: String,
+}
+
+impl UnknownEncoding {
+    /// The label which could not be resolved
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+impl fmt::Display for UnknownEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown encoding: {}", self.label)
+    }
+}
+
+impl StdError for UnknownEncoding {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn labels() {
+        let encoding = TextEncoding::from_labels("utf8", "sjis").unwrap();
+        assert_eq!(encoding.os(), encoding_rs::UTF_8);
+        assert_eq!(encoding.db(), encoding_rs::SHIFT_JIS);
+        assert_eq!(TextEncoding::default().db(), encoding_rs::UTF_8);
+    }
+
+    #[test]
+    fn ccsids() {
+        assert_eq!(encoding_for_ccsid(1208).unwrap(), encoding_rs::UTF_8);
+        assert_eq!(encoding_for_ccsid(943).unwrap(), encoding_rs::SHIFT_JIS);
+        assert_eq!(encoding_for_ccsid(819).unwrap(), encoding_rs::WINDOWS_1252);
+        assert_eq!(
+            TextEncoding::from_ccsid(1386).unwrap(),
+            TextEncoding::new(encoding_rs::GBK, encoding_rs::GBK)
+        );
+    }
+
+    #[test]
+    fn unmapped_ccsids() {
+        // EBCDIC (US) and UTF-16
+        assert_eq!(encoding_for_ccsid(37).unwrap_err().ccsid(), 37);
+        assert!(encoding_for_ccsid(1200).is_err());
+    }
+
+    #[test]
+    fn wide_text() {
+        let encoding = TextEncoding::default().with_wide_api(true);
+        assert!(encoding.is_wide());
+        let bytes = encoding.encode_text("Grüße, 世界");
+        assert_eq!(bytes.len(), 2 * to_wide("Grüße, 世界").len());
+        assert_eq!(encoding.decode_text(&bytes), "Grüße, 世界");
+        assert_eq!(from_wide(&to_wide("\u{1F600}")), "\u{1F600}");
+        // Narrow text is left to `db`
+        let narrow = TextEncoding::new(encoding_rs::UTF_8, encoding_rs::SHIFT_JIS);
+        assert_eq!(&narrow.encode_text("ｱ")[..], b"\xb1");
+    }
+
+    #[test]
+    fn unknown_label() {
+        let error = TextEncoding::from_labels("utf-8", "ebcdic-xyz").unwrap_err();
+        assert_eq!(error.label(), "ebcdic-xyz");
+    }
+}
@@ -1,5 +1,6 @@
 use super::ffi;
-use super::{safe, try_into_option, DiagnosticRecord, Environment, GetDiagRec, Result, Version3};
+use super::super::result::error_from_diagnostics;
+use super::{safe, try_into_option, Environment, Result, Version3};
 use std::cmp::max;
 use std::collections::HashMap;
 
@@ -40,11 +41,15 @@ impl Environment<Version3> {
         attributes
             .split('\0')
             .take_while(|kv_str| *kv_str != String::new())
-            .map(|kv_str| {
-                let mut iter = kv_str.split('=');
-                let key = iter.next().unwrap();
-                let value = iter.next().unwrap();
-                (key.to_string(), value.to_string())
+            .filter_map(|kv_str| {
+                let mut iter = kv_str.splitn(2, '=');
+                match (iter.next(), iter.next()) {
+                    (Some(key), Some(value)) => Some((key.to_string(), value.to_string())),
+                    _ => {
+                        warn!("Ignoring driver attribute without value: {}", kv_str);
+                        None
+                    }
+                }
             })
             .collect()
     }
@@ -152,17 +157,12 @@ impl Environment<Version3> {
         buf2: &'b mut [u8],
     ) -> Result<Option<(::std::borrow::Cow<'a, str>, ::std::borrow::Cow<'b, str>)>> {
         let result = f(&mut self.safe, direction, buf1, buf2);
+        let encoding = self.encoding().db();
         match try_into_option(result, self)? {
-            Some((len1, len2)) => unsafe {
-                Ok(Some((
-                    super::super::environment::DB_ENCODING
-                        .decode(&buf1[0..(len1 as usize)])
-                        .0,
-                    super::super::environment::DB_ENCODING
-                        .decode(&buf2[0..(len2 as usize)])
-                        .0,
-                )))
-            },
+            Some((len1, len2)) => Ok(Some((
+                encoding.decode(&buf1[0..(len1 as usize)]).0,
+                encoding.decode(&buf2[0..(len2 as usize)]).0,
+            ))),
             None => Ok(None),
         }
     }
@@ -201,9 +201,7 @@ impl Environment<Version3> {
                 }
                 safe::ReturnOption::NoData(()) => break,
                 safe::ReturnOption::Error(()) => {
-                    let diag = self.get_diag_rec(1).unwrap_or_else(DiagnosticRecord::empty);
-                    error!("{}", diag);
-                    return Err(diag.into());
+                    return Err(error_from_diagnostics(self, self.encoding()))
                 }
             }
 
@@ -237,4 +235,11 @@ mod test {
         assert_eq!(attributes["SQLLevel"], "1");
         assert_eq!(attributes["UsageCount"], "1");
     }
+
+    #[test]
+    fn parse_malformed_attributes() {
+        let attributes = Environment::parse_attributes("Setup\0Options=a=b\0\0");
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes["Options"], "a=b");
+    }
 }
@@ -1,16 +1,15 @@
 //! Implements the ODBC Environment
 mod list_data_sources;
 pub use self::list_data_sources::{DataSourceInfo, DriverInfo};
+use super::result::CallDiagnostics;
 use super::{
-    ffi, into_result, safe, try_into_option, DiagnosticRecord, GetDiagRec, Handle, Result,
+    ffi, into_result, safe, try_into_option, ClientInfo, DiagnosticRecord, GetDiagRec, Handle,
+    Result, Error, TextEncoding,
 };
 
 /// Environment state used to represent that environment has been set to odbc version 3
 pub type Version3 = safe::Odbc3;
 
-pub static mut OS_ENCODING: &encoding_rs::Encoding = encoding_rs::UTF_8;
-pub static mut DB_ENCODING: &encoding_rs::Encoding = encoding_rs::UTF_8;
-
 /// Handle to an ODBC Environment
 ///
 /// Creating an instance of this type is the first thing you do then using ODBC. The environment
@@ -18,6 +17,9 @@ pub static mut DB_ENCODING: &encoding_rs::Encoding = encoding_rs::UTF_8;
 #[derive(Debug)]
 pub struct Environment<V> {
     safe: safe::Environment<V>,
+    encoding: TextEncoding,
+    detect_encoding: bool,
+    client_info: Option<ClientInfo>,
 }
 
 impl<V> Handle for Environment<V> {
@@ -43,7 +45,7 @@ impl<V: safe::Version> Environment<V> {
     ///
     /// # Return
     ///
-    /// While most functions in this crate return a `DiagnosticRecord` in the event of an Error the
+    /// While most functions in this crate return an `Error` holding `DiagnosticRecord`s, the
     /// creation of an environment is special. Since `DiagnosticRecord`s are created using the
     /// environment, at least its allocation has to be successful to obtain one. If the allocation
     /// fails it is sadly not possible to receive further Diagnostics. Setting an unsupported version
@@ -61,13 +63,63 @@ impl<V: safe::Version> Environment<V> {
             }
             safe::Error(()) => return Err(None.into()),
         };
-        let safe = into_result(safe.declare_version()).map_err(|err| Some(*err))?;
-        Ok(Environment { safe: *safe })
+        let safe = into_result(safe.declare_version(), TextEncoding::default())
+            .map_err(|err| err.database_error().map(|e| e.record().clone()))?;
+        Ok(Environment {
+            safe: *safe,
+            encoding: TextEncoding::default(),
+            detect_encoding: false,
+            client_info: None,
+        })
     }
 
     pub(crate) fn as_safe(&self) -> &safe::Environment<V> {
         &self.safe
     }
+
+    /// Encoding connections created with this environment start with, UTF-8 unless set
+    /// otherwise
+    pub fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+
+    /// Sets the encoding of the connections created from now on, see `TextEncoding`
+    pub fn set_encoding(&mut self, encoding: TextEncoding) {
+        self.encoding = encoding;
+    }
+
+    /// Whether connections detect their encoding when they are created
+    pub fn detects_encoding(&self) -> bool {
+        self.detect_encoding
+    }
+
+    /// Lets connections created from now on detect their encoding from the code pages reported
+    /// by the client, see `Connection::detect_encoding`. Connecting fails if the code page has no
+    /// matching encoding.
+    pub fn set_detect_encoding(&mut self, detect: bool) {
+        self.detect_encoding = detect;
+    }
+
+    /// Client information applied to every connection when it is created
+    pub fn client_info(&self) -> Option<&ClientInfo> {
+        self.client_info.as_ref()
+    }
+
+    /// Applies `client_info` to the connections created from now on, see
+    /// `Connection::set_client_info`. Connecting fails if it can not be applied.
+    pub fn set_client_info(&mut self, client_info: Option<ClientInfo>) {
+        self.client_info = client_info;
+    }
+}
+
+impl<V> CallDiagnostics for Environment<V> {
+    fn diag_handle(&self) -> Option<(ffi::HandleType, ffi::SQLHANDLE)> {
+        Some((ffi::SQL_HANDLE_ENV, safe::Handle::handle(self)))
+    }
+
+    fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
 }
 
 unsafe impl<V> safe::Handle for Environment<V> {
@@ -92,7 +144,7 @@ unsafe impl<V> safe::Handle for Environment<V> {
 ///
 /// # Return
 ///
-/// While most functions in this crate return a `DiagnosticRecord` in the event of an Error the
+/// While most functions in this crate return an `Error` holding `DiagnosticRecord`s, the
 /// creation of an environment is special. Since `DiagnosticRecord`s are created using the
 /// environment, at least its allocation has to be successful to obtain one. If the allocation
 /// fails it is sadly not possible to receive further Diagnostics. Setting an unsupported version
@@ -102,13 +154,20 @@ pub fn create_environment_v3(
     Environment::new()
 }
 
+/// Like `create_environment_v3`, but sets the encoding of the environment to the encodings with
+/// the given labels, see `TextEncoding::from_labels`
+///
+/// Fails with an `UnknownEncoding` wrapped in `Error::Other` if a label is unknown, and with the
+/// diagnostic record as `Error` if the environment can not be created.
 pub fn create_environment_v3_with_os_db_encoding(
     os_encoding: &str,
     db_encoding: &str,
-) -> std::result::Result<Environment<Version3>, Box<Option<DiagnosticRecord>>> {
-    unsafe {
-        OS_ENCODING = encoding_rs::Encoding::for_label(os_encoding.as_bytes()).unwrap();
-        DB_ENCODING = encoding_rs::Encoding::for_label(db_encoding.as_bytes()).unwrap();
-    }
-    Environment::new()
+) -> Result<Environment<Version3>> {
+    let encoding = TextEncoding::from_labels(os_encoding, db_encoding)?;
+    let mut env = create_environment_v3().map_err(|err| match *err {
+        Some(record) => Error::from_records(vec![record]),
+        None => Error::other("Unable to allocate an ODBC environment"),
+    })?;
+    env.set_encoding(encoding);
+    Ok(env)
 }
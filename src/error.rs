@@ -0,0 +1,486 @@
+//! Error type returned by the functions of this crate
+use super::{
+    ffi, DiagnosticHeader, DiagnosticRecord, NotSupported, Sqlca, UnknownCcsid, UnknownEncoding,
+};
+use std::error::Error as StdError;
+use std::{fmt, str};
+
+/// Error returned by the functions of this crate
+///
+/// Errors reported by the CLI are classified by the class of their SQLSTATE, i.e. its first two
+/// characters. Every variant wrapping a `DatabaseError` carries all diagnostic records of the
+/// failed call.
+///
+/// ```no_run
+/// # use ibm_db::{Connection, Error, Statement, safe::AutocommitOn};
+/// # fn insert(conn: &Connection<AutocommitOn>) -> ibm_db::Result<()> {
+/// match Statement::with_parent(conn)?.exec_direct("INSERT INTO T VALUES (1)") {
+///     Ok(_) => (),
+///     Err(ref e) if e.is_unique_violation() => println!("Already there"),
+///     Err(Error::TransactionRollback(e)) => println!("Rolled back: {}", e),
+///     Err(e) => return Err(e),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// Connection exception (class `08`), e.g. the connection to the server has been lost
+    Connection(DatabaseError),
+    /// Invalid authorization specification (class `28`)
+    Authorization(DatabaseError),
+    /// Data exception (class `22`), e.g. a numeric overflow or an invalid datetime value
+    Data(DatabaseError),
+    /// Integrity constraint violation (class `23`), e.g. a duplicate key (`23505`, `SQL0803N`)
+    IntegrityConstraint(DatabaseError),
+    /// Transaction rollback (class `40`), e.g. a deadlock or lock timeout (`40001`, `SQL0911N`)
+    TransactionRollback(DatabaseError),
+    /// Syntax error or access rule violation (class `42`), e.g. a syntax error (`42601`) or an
+    /// undefined object (`42704`)
+    SyntaxOrAccess(DatabaseError),
+    /// Resource not available (class `57`), e.g. a table space which is not accessible
+    ResourceUnavailable(DatabaseError),
+    /// Any other error reported by the CLI
+    Database(DatabaseError),
+    /// The data source does not support a CLI function a higher level API depends on
+    NotSupported(NotSupported),
+    /// A CLI function returned a code it is not expected to return, e.g. `SQL_NEED_DATA` for a
+    /// statement without data-at-execution parameters or `SQL_INVALID_HANDLE`
+    UnexpectedReturn {
+        /// Name of the CLI function, e.g. `SQLExecute`
+        function: &'static str,
+        /// The returned `SQLRETURN` value
+        code: i16,
+    },
+    /// Data returned by the CLI could not be converted into the requested type, e.g. a buffer of
+    /// the wrong size for a date or a string which cannot be borrowed as `&str`
+    Conversion(String),
+    /// An error not reported by the CLI, e.g. an invalid argument
+    Other(Box<dyn StdError + Send + Sync>),
+}
+
+impl Error {
+    /// Classifies the diagnostic records of a failed call. The first record determines the
+    /// variant. An empty list is replaced by `DiagnosticRecord::empty`.
+    pub fn from_records(records: Vec<DiagnosticRecord>) -> Error {
+        let error = DatabaseError::from_records(records);
+        match error.sqlstate().class() {
+            "08" => Error::Connection(error),
+            "28" => Error::Authorization(error),
+            "22" => Error::Data(error),
+            "23" => Error::IntegrityConstraint(error),
+            "40" => Error::TransactionRollback(error),
+            "42" => Error::SyntaxOrAccess(error),
+            "57" => Error::ResourceUnavailable(error),
+            _ => Error::Database(error),
+        }
+    }
+
+    /// Wraps an error which has not been reported by the CLI
+    pub fn other<E>(error: E) -> Error
+    where
+        E: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        Error::Other(error.into())
+    }
+
+    pub(crate) fn unexpected_return(function: &'static str, ret: ffi::SQLRETURN) -> Error {
+        Error::UnexpectedReturn {
+            function,
+            code: ret as i16,
+        }
+    }
+
+    /// The diagnostic records, if the error has been reported by the CLI
+    pub fn database_error(&self) -> Option<&DatabaseError> {
+        match self {
+            Error::Connection(e)
+            | Error::Authorization(e)
+            | Error::Data(e)
+            | Error::IntegrityConstraint(e)
+            | Error::TransactionRollback(e)
+            | Error::SyntaxOrAccess(e)
+            | Error::ResourceUnavailable(e)
+            | Error::Database(e) => Some(e),
+            Error::NotSupported(_)
+            | Error::UnexpectedReturn { .. }
+            | Error::Conversion(_)
+            | Error::Other(_) => None,
+        }
+    }
+
+    /// SQLSTATE of the first diagnostic record
+    pub fn sqlstate(&self) -> Option<SqlState> {
+        self.database_error().map(DatabaseError::sqlstate)
+    }
+
+    /// DB2 SQLCODE of the first diagnostic record, see `DatabaseError::sqlcode`
+    pub fn sqlcode(&self) -> Option<i32> {
+        self.database_error().and_then(DatabaseError::sqlcode)
+    }
+
+    /// SQLCA of the failed call, see `DatabaseError::sqlca`
+    pub fn sqlca(&self) -> Option<&Sqlca> {
+        self.database_error().and_then(DatabaseError::sqlca)
+    }
+
+    /// Diagnostic header of the failed call, see `DatabaseError::header`
+    pub fn diagnostic_header(&self) -> Option<&DiagnosticHeader> {
+        self.database_error().and_then(DatabaseError::header)
+    }
+
+    /// Attaches the SQLCA of the failed call
+    pub(crate) fn with_sqlca(mut self, sqlca: Sqlca) -> Error {
+        if let Some(e) = self.database_error_mut() {
+            e.sqlca = Some(Box::new(sqlca));
+        }
+        self
+    }
+
+    /// Attaches the diagnostic header of the failed call
+    pub(crate) fn with_header(mut self, header: DiagnosticHeader) -> Error {
+        if let Some(e) = self.database_error_mut() {
+            e.header = Some(Box::new(header));
+        }
+        self
+    }
+
+    fn database_error_mut(&mut self) -> Option<&mut DatabaseError> {
+        match self {
+            Error::Connection(e)
+            | Error::Authorization(e)
+            | Error::Data(e)
+            | Error::IntegrityConstraint(e)
+            | Error::TransactionRollback(e)
+            | Error::SyntaxOrAccess(e)
+            | Error::ResourceUnavailable(e)
+            | Error::Database(e) => Some(e),
+            Error::NotSupported(_)
+            | Error::UnexpectedReturn { .. }
+            | Error::Conversion(_)
+            | Error::Other(_) => None,
+        }
+    }
+
+    /// `true` for a duplicate value in a unique index or constraint (`23505`, `SQL0803N`)
+    pub fn is_unique_violation(&self) -> bool {
+        self.has_sqlstate(&["23505"]) || self.sqlcode() == Some(-803)
+    }
+
+    /// `true` if the transaction has been rolled back because of a deadlock or lock timeout
+    /// (`40001`, `SQL0911N`), or the statement failed due to one (`57033`, `SQL0913N`)
+    pub fn is_deadlock(&self) -> bool {
+        match self.sqlcode() {
+            Some(-911) | Some(-913) => true,
+            _ => self.has_sqlstate(&["40001", "57033"]),
+        }
+    }
+
+    /// `true` if the connection has been re-established by automatic client reroute
+    /// (`SQL30108N`, `08506`). The transaction has been rolled back and can be run again.
+    pub fn is_client_reroute(&self) -> bool {
+        self.sqlcode() == Some(-30108) || self.has_sqlstate(&["08506"])
+    }
+
+    /// `true` for errors which are likely to disappear if the failed operation is run again,
+    /// i.e. deadlocks, lock timeouts and client reroutes. See `RetryPolicy`.
+    pub fn is_transient(&self) -> bool {
+        self.is_deadlock() || self.is_client_reroute()
+    }
+
+    /// `true` if the connection to the server is broken (class `08`)
+    pub fn is_connection_lost(&self) -> bool {
+        matches!(self, Error::Connection(_))
+    }
+
+    /// `true` for a syntax error in a statement (`42601`)
+    pub fn is_syntax_error(&self) -> bool {
+        self.has_sqlstate(&["42601"])
+    }
+
+    fn has_sqlstate(&self, states: &[&str]) -> bool {
+        match self.sqlstate() {
+            Some(state) => states.contains(&state.as_str()),
+            None => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.database_error() {
+            Some(e) => e.fmt(f),
+            None => match self {
+                Error::NotSupported(e) => e.fmt(f),
+                Error::UnexpectedReturn { function, code } => {
+                    write!(f, "{} returned unexpected result {}", function, code)
+                }
+                Error::Conversion(message) => f.write_str(message),
+                Error::Other(e) => e.fmt(f),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::NotSupported(_) | Error::UnexpectedReturn { .. } | Error::Conversion(_) => None,
+            Error::Other(e) => Some(e.as_ref()),
+            _ => self.database_error().and_then(|e| e.source()),
+        }
+    }
+}
+
+impl From<NotSupported> for Error {
+    fn from(e: NotSupported) -> Self {
+        Error::NotSupported(e)
+    }
+}
+
+impl From<UnknownEncoding> for Error {
+    fn from(e: UnknownEncoding) -> Self {
+        Error::Other(Box::new(e))
+    }
+}
+
+impl From<UnknownCcsid> for Error {
+    fn from(e: UnknownCcsid) -> Self {
+        Error::Other(Box::new(e))
+    }
+}
+
+/// The diagnostic records of a failed call. The first record is displayed, the remaining ones
+/// are available as the chain of `source` errors or via `records`.
+///
+/// The payload is boxed, so results carrying an `Error` stay small.
+pub struct DatabaseError {
+    record: Box<DiagnosticRecord>,
+    next: Option<Box<DatabaseError>>,
+    sqlca: Option<Box<Sqlca>>,
+    header: Option<Box<DiagnosticHeader>>,
+}
+
+impl DatabaseError {
+    fn from_records(records: Vec<DiagnosticRecord>) -> DatabaseError {
+        let mut records = records.into_iter().rev();
+        let last = records.next().unwrap_or_else(DiagnosticRecord::empty);
+        records.fold(
+            DatabaseError {
+                record: Box::new(last),
+                next: None,
+                sqlca: None,
+                header: None,
+            },
+            |next, record| DatabaseError {
+                record: Box::new(record),
+                next: Some(Box::new(next)),
+                sqlca: None,
+                header: None,
+            },
+        )
+    }
+
+    /// The first diagnostic record
+    pub fn record(&self) -> &DiagnosticRecord {
+        &self.record
+    }
+
+    /// All diagnostic records in the order reported by the CLI
+    pub fn records(&self) -> impl Iterator<Item = &DiagnosticRecord> {
+        let mut current = Some(self);
+        std::iter::from_fn(move || {
+            let error = current?;
+            current = error.next.as_deref();
+            Some(&*error.record)
+        })
+    }
+
+    /// SQLSTATE of the first diagnostic record
+    pub fn sqlstate(&self) -> SqlState {
+        SqlState::from_record(&self.record)
+    }
+
+    /// DB2 SQLCODE of the first diagnostic record. This is the native error code reported by the
+    /// CLI, or extracted from the message token like `SQL0803N` if the native code is `0`, or
+    /// taken from the SQLCA. Negative for errors and positive for warnings. `None` for errors
+    /// raised by the CLI driver itself.
+    pub fn sqlcode(&self) -> Option<i32> {
+        record_sqlcode(&self.record).or_else(|| self.sqlca.as_ref().map(|sqlca| sqlca.sqlcode))
+    }
+
+    /// SQLCA of the failed call, e.g. for the message tokens of the SQLCODE. Only available for
+    /// errors of statements and connections which have been reported by the server.
+    pub fn sqlca(&self) -> Option<&Sqlca> {
+        self.sqlca.as_deref()
+    }
+
+    /// Header fields of the diagnostic data of the failed call, e.g. the kind of statement
+    /// executed. Only available for errors of statements, connections and environments.
+    pub fn header(&self) -> Option<&DiagnosticHeader> {
+        self.header.as_deref()
+    }
+
+    /// Message of the first diagnostic record
+    pub fn message(&self) -> &str {
+        self.record.get_message()
+    }
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.record.fmt(f)
+    }
+}
+
+impl fmt::Debug for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.records()).finish()
+    }
+}
+
+impl StdError for DatabaseError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.next
+            .as_ref()
+            .map(|next| &**next as &(dyn StdError + 'static))
+    }
+}
+
+/// Five character SQLSTATE of a diagnostic record
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct SqlState([u8; 5]);
+
+impl SqlState {
+    fn from_record(record: &DiagnosticRecord) -> SqlState {
+        let mut state = [b'0'; 5];
+        state.copy_from_slice(&record.get_raw_state()[..5]);
+        SqlState(state)
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Drivers only report ASCII characters
+        str::from_utf8(&self.0).unwrap_or("HY000")
+    }
+
+    /// The class of the SQLSTATE, i.e. its first two characters, e.g. `23` for integrity
+    /// constraint violations
+    pub fn class(&self) -> &str {
+        &self.as_str()[..2]
+    }
+}
+
+impl PartialEq<&str> for SqlState {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for SqlState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for SqlState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SqlState({})", self.as_str())
+    }
+}
+
+/// Native error of records generated by the CLI itself, e.g. `CLI0108E`, which carry no SQLCODE
+const CLI_NATIVE_ERROR: i32 = -99999;
+
+/// DB2 SQLCODE of a diagnostic record, see `DatabaseError::sqlcode`
+pub(crate) fn record_sqlcode(record: &DiagnosticRecord) -> Option<i32> {
+    match record.get_native_error() {
+        0 => parse_sqlcode(record.get_message()),
+        CLI_NATIVE_ERROR => None,
+        code => Some(code),
+    }
+}
+
+/// Extracts the SQLCODE from a DB2 message token like `SQL0803N` or `SQL20157W`
+fn parse_sqlcode(message: &str) -> Option<i32> {
+    let bytes = message.as_bytes();
+    for (start, _) in message.match_indices("SQL") {
+        if start > 0 && bytes[start - 1].is_ascii_alphanumeric() {
+            continue;
+        }
+        let digits = bytes[start + 3..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if !(4..=5).contains(&digits) {
+            continue;
+        }
+        let end = start + 3 + digits;
+        let sign = match bytes.get(end) {
+            Some(b'N') | Some(b'C') => -1,
+            Some(b'W') => 1,
+            _ => continue,
+        };
+        if matches!(bytes.get(end + 1), Some(b) if b.is_ascii_alphanumeric()) {
+            continue;
+        }
+        return message[start + 3..end]
+            .parse::<i32>()
+            .ok()
+            .map(|code| sign * code);
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sqlcode_from_message() {
+        let duplicate = "[IBM][CLI Driver][DB2/LINUXX8664] SQL0803N  One or more values in the \
+                         INSERT statement are not valid.  SQLSTATE=23505";
+        assert_eq!(parse_sqlcode(duplicate), Some(-803));
+        let deadlock = "[IBM][CLI Driver][DB2/LINUXX8664] SQL0911N  The current transaction has \
+                        been rolled back.  SQLSTATE=40001";
+        assert_eq!(parse_sqlcode(deadlock), Some(-911));
+        assert_eq!(parse_sqlcode("SQL20157W  Warning"), Some(20157));
+        assert_eq!(
+            parse_sqlcode("SQL30081N  Communication error"),
+            Some(-30081)
+        );
+    }
+
+    #[test]
+    fn sqlcode_missing() {
+        let cli = "[IBM][CLI Driver] CLI0108E  Communication link failure. SQLSTATE=08S01";
+        assert_eq!(parse_sqlcode(cli), None);
+        assert_eq!(parse_sqlcode("SQLSTATE=42601"), None);
+        assert_eq!(parse_sqlcode("MYSQL0803N"), None);
+        assert_eq!(parse_sqlcode("SQL080N"), None);
+    }
+
+    #[test]
+    fn sqlcode_of_record() {
+        let state: Vec<u16> = "08S01".encode_utf16().collect();
+        let cli = DiagnosticRecord::from_text(
+            &state,
+            -99999,
+            "[IBM][CLI Driver] CLI0108E  Communication link failure. SQLSTATE=08S01".to_string(),
+        );
+        assert_eq!(record_sqlcode(&cli), None);
+        let server = DiagnosticRecord::from_text(&state, -30081, String::new());
+        assert_eq!(record_sqlcode(&server), Some(-30081));
+    }
+
+    #[test]
+    fn unexpected_return() {
+        let error = Error::unexpected_return("SQLExecDirect", ffi::SQL_NEED_DATA);
+        assert_eq!(
+            error.to_string(),
+            "SQLExecDirect returned unexpected result 99"
+        );
+        assert!(error.database_error().is_none());
+        assert!(!error.is_transient());
+    }
+}
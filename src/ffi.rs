@@ -1,3 +1,11 @@
 //! Reexport odbc-sys as ffi
 extern crate odbc_sys;
 pub use self::odbc_sys::*;
+
+/// Passes a handle owned by odbc-safe to the CLI prototypes of the crate root, which declare
+/// handles as `SQLINTEGER`. Those prototypes are used for `SQLGetInfo`, `SQLSetConnectAttr`,
+/// `SQLGetConnectAttr` and their wide variants, because odbc-sys types the info type and attribute
+/// as enums, which can not hold the DB2 specific values.
+pub fn cli_handle<T>(handle: *mut T) -> crate::SQLHANDLE {
+    handle as usize as crate::SQLHANDLE
+}
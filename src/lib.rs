@@ -13,35 +13,53 @@
 
 #[macro_use]
 extern crate log;
-#[macro_use]
-extern crate lazy_static;
 
-use std::error::Error;
+use std::error::Error as StdError;
 use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
 
 extern crate encoding_rs;
 pub extern crate odbc_safe;
 
-pub use connection::Connection;
-pub use diagnostics::{DiagnosticRecord, GetDiagRec};
+pub use communication_area::Sqlca;
+pub use connection::{
+    AccessMode, ClientInfo, Codepages, ConcurrentAccessResolution, Connection, CursorBehavior,
+    CursorCapabilities, IdentifierLengths, IsolationLevel, IsolationLevels, IsolationOverride,
+    LockTimeout, NotSupported, Savepoint, SupportedFunctions, Transaction, TransactionOutcome,
+};
+pub use connection_string::{Authentication, ConnectionString, ParseError, Protocol};
+pub use diagnostics::{DiagnosticHeader, DiagnosticRecord, GetDiagRec};
+pub use encoding::{encoding_for_ccsid, TextEncoding, UnknownCcsid, UnknownEncoding};
 pub use environment::*;
+pub use error::{DatabaseError, Error, SqlState};
 pub use ffi::*;
 pub use result::Result;
+pub use retry::RetryPolicy;
 pub use statement::*;
+pub use warnings::WarningPolicy;
 
 use odbc_object::OdbcObject;
 pub use odbc_safe as safe;
 use raii::Raii;
 use result::{into_result, try_into_option, Return};
 
+mod communication_area;
 mod connection;
+mod connection_string;
 mod diagnostics;
+pub mod dsdriver;
+mod encoding;
 mod environment;
+mod error;
 mod ffi;
+#[cfg(feature = "async")]
+pub mod nonblocking;
 mod odbc_object;
 mod raii;
 mod result;
+mod retry;
 mod statement;
+mod warnings;
 
 /// Reflects the ability of a type to expose a valid handle
 pub trait Handle {
@@ -51,17 +69,107 @@ pub trait Handle {
 }
 //Added for connection pooling
 
+/// Settings shared by `ODBCConnectionManager` and `ODBCConnectionManagerTx`
 #[derive(Debug)]
-pub struct ODBCConnectionManager {
+pub struct PoolConfig {
     connection_string: String,
+    env: LazyEnv,
+    client_info: Option<ClientInfo>,
+    validation_query: Option<String>,
+    reset_session: bool,
+}
+
+#[derive(Debug)]
+pub struct ODBCConnectionManager {
+    config: PoolConfig,
+    reset: Option<ConnectionHook<safe::AutocommitOn>>,
 }
 
+/// Connection manager for connections with autocommit disabled
+///
+/// Work left uncommitted by a user is only rolled back when the connection is checked out again,
+/// so commit or roll back before returning a connection to avoid holding locks while it is idle.
 #[derive(Debug)]
 pub struct ODBCConnectionManagerTx {
-    connection_string: String,
+    config: PoolConfig,
+    reset: Option<ConnectionHook<safe::AutocommitOff>>,
+}
+
+/// Callback run on pooled connections, see `ConnectionInit` and
+/// `ODBCConnectionManager::with_reset`
+struct ConnectionHook<AC: safe::AutocommitMode>(HookFn<AC>);
+
+type HookFn<AC> = Box<dyn Fn(&mut Connection<'static, AC>) -> HookResult + Send + Sync>;
+
+/// Outcome of a `ConnectionHook`. An error causes the connection to be discarded.
+type HookResult = std::result::Result<(), Box<dyn StdError>>;
+
+impl<AC: safe::AutocommitMode> fmt::Debug for ConnectionHook<AC> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ConnectionHook")
+    }
+}
+
+/// Initializes every new pooled connection, e.g. to set `CURRENT SCHEMA` or the isolation level,
+/// and commits whatever it did. A connection is discarded if the initialization fails.
+///
+/// ```no_run
+/// # use ibm_db::{ConnectionInit, ODBCConnectionManager, Statement};
+/// let manager = ODBCConnectionManager::new("DSN=sample");
+/// let pool = r2d2::Pool::builder()
+///     .connection_customizer(Box::new(ConnectionInit::new(|conn| {
+///         Statement::with_parent(&*conn)?.exec_direct("SET CURRENT SCHEMA = APP")?;
+///         Ok(())
+///     })))
+///     .build(manager);
+/// ```
+///
+/// Resetting the session with `PoolConfig::with_session_reset` undoes these settings, apply them
+/// again with the reset hook of the manager.
+pub struct ConnectionInit<AC: safe::AutocommitMode>(ConnectionHook<AC>);
+
+impl<AC: safe::AutocommitMode> ConnectionInit<AC> {
+    pub fn new<F>(init: F) -> ConnectionInit<AC>
+    where
+        F: Fn(&mut Connection<'static, AC>) -> HookResult + Send + Sync + 'static,
+    {
+        ConnectionInit(ConnectionHook(Box::new(init)))
+    }
+}
+
+impl<AC: safe::AutocommitMode> fmt::Debug for ConnectionInit<AC> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ConnectionInit")
+    }
+}
+
+impl<AC> r2d2::CustomizeConnection<ODBCConnection<'static, AC>, ODBCError> for ConnectionInit<AC>
+where
+    AC: safe::AutocommitMode + 'static,
+{
+    fn on_acquire(
+        &self,
+        conn: &mut ODBCConnection<'static, AC>,
+    ) -> std::result::Result<(), ODBCError> {
+        ((self.0).0)(&mut conn.0).map_err(ODBCError)?;
+        conn.0.end_transaction(SQL_COMMIT)?;
+        Ok(())
+    }
 }
 
-pub struct ODBCConnection<'a, AC: safe::AutocommitMode>(Connection<'a, AC>);
+/// Statement used by the connection managers to validate connections unless configured otherwise
+const DEFAULT_VALIDATION_QUERY: &str = "VALUES 1";
+
+/// A pooled connection. Keeps the environment it has been created in alive, the connection is
+/// dropped first as fields are dropped in declaration order. The third field holds the client
+/// information to restore before the connection is handed out again, the last one whether it has
+/// been handed out before and has to be reset.
+pub struct ODBCConnection<'a, AC: safe::AutocommitMode>(
+    Connection<'a, AC>,
+    Arc<ODBCEnv>,
+    Option<ClientInfo>,
+    bool,
+);
 
 unsafe impl Send for ODBCConnection<'static, safe::AutocommitOn> {}
 unsafe impl Send for ODBCConnection<'static, safe::AutocommitOff> {}
@@ -70,22 +178,153 @@ impl<'a, AC: safe::AutocommitMode> ODBCConnection<'a, AC> {
     pub fn raw(&self) -> &Connection<'a, AC> {
         &self.0
     }
+
+    /// Changes the client information for this checkout, e.g. to report the end user a pooled
+    /// connection is currently used for. The previous information is restored when the
+    /// connection is returned to the pool.
+    pub fn set_client_info(&mut self, client_info: &ClientInfo) -> Result<()> {
+        if self.2.is_none() {
+            self.2 = Some(self.0.client_info()?);
+        }
+        self.0.set_client_info(client_info)
+    }
+
+    /// Undoes `set_client_info`, so the next checkout starts with the information applied by
+    /// the connection manager
+    fn restore_client_info(&mut self) -> Result<()> {
+        if let Some(client_info) = self.2.take() {
+            self.0.set_client_info(&client_info)?;
+        }
+        Ok(())
+    }
+
+    /// Fails if the connection is dead or `query` can not be executed
+    fn validate(&self, query: Option<&str>) -> std::result::Result<(), ODBCError> {
+        if self.0.is_dead()? {
+            return Err(ODBCError("Connection is dead".into()));
+        }
+        if let Some(query) = query {
+            Statement::with_parent(&self.0).and_then(|stmt| stmt.exec_direct(query))?;
+        }
+        Ok(())
+    }
+
+    /// Connections are considered broken if the driver reports them dead, or if that can not be
+    /// determined
+    fn has_broken(&self) -> bool {
+        self.0.is_dead().unwrap_or(true)
+    }
 }
 
+/// An environment which can be shared between connection managers
+#[derive(Debug)]
 pub struct ODBCEnv(Environment<Version3>);
 
 unsafe impl Sync for ODBCEnv {}
 
 unsafe impl Send for ODBCEnv {}
 
+impl ODBCEnv {
+    /// Creates an environment with the default encodings
+    pub fn new() -> std::result::Result<ODBCEnv, ODBCError> {
+        create_environment_v3()
+            .map(ODBCEnv)
+            .map_err(ODBCError::from_environment_error)
+    }
+
+    /// Creates an environment with the given encodings, see
+    /// `create_environment_v3_with_os_db_encoding`
+    pub fn with_os_db_encoding(
+        os_encoding: &str,
+        db_encoding: &str,
+    ) -> std::result::Result<ODBCEnv, ODBCError> {
+        create_environment_v3_with_os_db_encoding(os_encoding, db_encoding)
+            .map(ODBCEnv)
+            .map_err(|e| ODBCError(Box::new(e)))
+    }
+
+    pub fn environment(&self) -> &Environment<Version3> {
+        &self.0
+    }
+
+    /// Connects within this environment. The lifetime of the connection is extended to
+    /// `'static`, which is sound as long as the returned connection is stored together with an
+    /// `Arc` of the environment, like `ODBCConnection` does.
+    fn connect(
+        &self,
+        connection_string: &str,
+    ) -> std::result::Result<Connection<'static, safe::AutocommitOn>, ODBCError> {
+        let conn = self.0.connect_with_connection_string(connection_string)?;
+        Ok(unsafe {
+            std::mem::transmute::<
+                Connection<'_, safe::AutocommitOn>,
+                Connection<'static, safe::AutocommitOn>,
+            >(conn)
+        })
+    }
+}
+
+impl From<Environment<Version3>> for ODBCEnv {
+    fn from(env: Environment<Version3>) -> Self {
+        ODBCEnv(env)
+    }
+}
+
+/// The environment of a connection manager, either passed in or created on first use
+#[derive(Debug, Default)]
+struct LazyEnv {
+    env: Mutex<Option<Arc<ODBCEnv>>>,
+    encodings: Option<(String, String)>,
+    detect_encoding: bool,
+    wide: bool,
+}
+
+impl LazyEnv {
+    fn get(&self) -> std::result::Result<Arc<ODBCEnv>, ODBCError> {
+        // The lock only guards the creation, a panic while holding it leaves nothing inconsistent
+        let mut env = self.env.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(env) = &*env {
+            return Ok(env.clone());
+        }
+        let mut created = match &self.encodings {
+            Some((os_encoding, db_encoding)) => {
+                ODBCEnv::with_os_db_encoding(os_encoding, db_encoding)?
+            }
+            None => ODBCEnv::new()?,
+        };
+        created.0.set_detect_encoding(self.detect_encoding);
+        let encoding = created.0.encoding().with_wide_api(self.wide);
+        created.0.set_encoding(encoding);
+        let created = Arc::new(created);
+        *env = Some(created.clone());
+        Ok(created)
+    }
+}
+
+impl From<Arc<ODBCEnv>> for LazyEnv {
+    fn from(env: Arc<ODBCEnv>) -> Self {
+        LazyEnv {
+            env: Mutex::new(Some(env)),
+            encodings: None,
+            detect_encoding: false,
+            wide: false,
+        }
+    }
+}
+
 #[derive(Debug)]
-pub struct ODBCError(Box<dyn Error>);
+pub struct ODBCError(Box<dyn StdError>);
 
-lazy_static! {
-    static ref ENV: ODBCEnv = ODBCEnv(create_environment_v3().unwrap());
+impl ODBCError {
+    fn from_environment_error(err: Box<Option<DiagnosticRecord>>) -> ODBCError {
+        match *err {
+            Some(diag) => ODBCError(Box::new(diag)),
+            None => ODBCError("Unable to allocate an ODBC environment".into()),
+        }
+    }
 }
 
-impl Error for ODBCError {
+impl StdError for ODBCError {
     fn description(&self) -> &str {
         "Error connecting DB"
     }
@@ -97,9 +336,8 @@ impl fmt::Display for ODBCError {
     }
 }
 
-impl From<Box<DiagnosticRecord>> for ODBCError {
-    fn from(err: Box<DiagnosticRecord>) -> Self {
-        println!("ODBC ERROR {}", err);
+impl From<Error> for ODBCError {
+    fn from(err: Error) -> Self {
         ODBCError(Box::new(err))
     }
 }
@@ -110,42 +348,209 @@ impl<E: 'static> From<std::sync::PoisonError<E>> for ODBCError {
     }
 }
 
+impl PoolConfig {
+    /// Creates the settings for connections to `connection_string`
+    pub fn new<S: Into<String>>(connection_string: S) -> PoolConfig {
+        PoolConfig {
+            connection_string: connection_string.into(),
+            env: LazyEnv::default(),
+            client_info: None,
+            validation_query: Some(DEFAULT_VALIDATION_QUERY.to_string()),
+            reset_session: false,
+        }
+    }
+
+    /// Creates connections within `env` instead of an environment owned by the manager. The
+    /// environment can be shared between several managers.
+    pub fn with_env(mut self, env: Arc<ODBCEnv>) -> PoolConfig {
+        self.env = LazyEnv::from(env);
+        self
+    }
+
+    /// Creates the environment owned by the manager with the given encodings, see
+    /// `create_environment_v3_with_os_db_encoding`. Ignored if an environment is passed with
+    /// `with_env`.
+    pub fn with_os_db_encoding(mut self, os_encoding: &str, db_encoding: &str) -> PoolConfig {
+        self.env.encodings = Some((os_encoding.to_string(), db_encoding.to_string()));
+        self
+    }
+
+    /// Lets the environment owned by the manager detect the encoding of every new connection,
+    /// see `Environment::set_detect_encoding`. Ignored if an environment is passed with
+    /// `with_env`.
+    pub fn with_encoding_detection(mut self) -> PoolConfig {
+        self.env.detect_encoding = true;
+        self
+    }
+
+    /// Lets the environment owned by the manager exchange text through the wide functions, see
+    /// `TextEncoding::with_wide_api`. Ignored if an environment is passed with `with_env`.
+    pub fn with_wide_api(mut self) -> PoolConfig {
+        self.env.wide = true;
+        self
+    }
+
+    /// Client information applied to every new connection. Use
+    /// `ODBCConnection::set_client_info` to change it for a single checkout.
+    pub fn with_client_info(mut self, client_info: ClientInfo) -> PoolConfig {
+        self.client_info = Some(client_info);
+        self
+    }
+
+    /// Statement executed by `is_valid` to check a connection, `VALUES 1` by default. Use e.g.
+    /// `SELECT 1 FROM SYSIBM.SYSDUMMY1` for servers which do not support `VALUES`.
+    pub fn with_validation_query<S: Into<String>>(mut self, query: S) -> PoolConfig {
+        self.validation_query = Some(query.into());
+        self
+    }
+
+    /// Only checks `SQL_ATTR_CONNECTION_DEAD` in `is_valid`, without a round trip to the server
+    pub fn without_validation_query(mut self) -> PoolConfig {
+        self.validation_query = None;
+        self
+    }
+
+    /// Resets the session state with `SQL_ATTR_RESET_CONNECTION` on a connection returned to the
+    /// pool before it is handed out again, so special registers or temporary tables do not leak
+    /// to the next user. Client information is applied again afterwards, followed by the reset
+    /// hook of the manager. Connections are discarded instead if the server does not support
+    /// resetting them. Like the reset hook, this requires `test_on_check_out`.
+    pub fn with_session_reset(mut self) -> PoolConfig {
+        self.reset_session = true;
+        self
+    }
+
+    /// Connects and applies the client information
+    fn connect(
+        &self,
+    ) -> std::result::Result<(Connection<'static, safe::AutocommitOn>, Arc<ODBCEnv>), ODBCError>
+    {
+        let env = self.env.get()?;
+        let mut conn = env.connect(&self.connection_string)?;
+        self.apply_client_info(&mut conn)?;
+        Ok((conn, env))
+    }
+
+    fn apply_client_info<AC: safe::AutocommitMode>(
+        &self,
+        conn: &mut Connection<'static, AC>,
+    ) -> Result<()> {
+        if let Some(client_info) = &self.client_info {
+            conn.set_client_info(client_info)?;
+        }
+        Ok(())
+    }
+}
+
 impl ODBCConnectionManager {
     /// Creates a new `ODBCConnectionManager`.
     pub fn new<S: Into<String>>(connection_string: S) -> ODBCConnectionManager {
+        ODBCConnectionManager::with_config(PoolConfig::new(connection_string))
+    }
+
+    /// Creates a manager with the given settings
+    pub fn with_config(config: PoolConfig) -> ODBCConnectionManager {
         ODBCConnectionManager {
-            connection_string: connection_string.into(),
+            config,
+            reset: None,
         }
     }
-}
 
-impl ODBCConnectionManagerTx {
-    /// Creates a new `ODBCConnectionManagerTx`.
-    pub fn new<S: Into<String>>(connection_string: S) -> ODBCConnectionManagerTx {
-        ODBCConnectionManagerTx {
-            connection_string: connection_string.into(),
+    /// Runs `reset` on a connection returned to the pool before it is handed out again. A
+    /// connection is discarded if `reset` fails.
+    ///
+    /// Connections are reset when r2d2 checks them out, so this requires `test_on_check_out`,
+    /// which is enabled by default.
+    pub fn with_reset<F>(mut self, reset: F) -> ODBCConnectionManager
+    where
+        F: Fn(&mut Connection<'static, safe::AutocommitOn>) -> HookResult + Send + Sync + 'static,
+    {
+        self.reset = Some(ConnectionHook(Box::new(reset)));
+        self
+    }
+
+    /// Prepares a returned connection for the next user. r2d2 does not notify the manager when
+    /// a connection is returned, so this is done by `is_valid` when it is checked out again.
+    fn release(
+        &self,
+        conn: &mut Connection<'static, safe::AutocommitOn>,
+    ) -> std::result::Result<(), ODBCError> {
+        if self.config.reset_session {
+            conn.reset_session()?;
+            self.config.apply_client_info(conn)?;
+        }
+        if let Some(reset) = &self.reset {
+            (reset.0)(conn).map_err(ODBCError)?;
         }
+        Ok(())
     }
 }
+
 impl r2d2::ManageConnection for ODBCConnectionManager {
     type Connection = ODBCConnection<'static, safe::AutocommitOn>;
     type Error = ODBCError;
 
     fn connect(&self) -> std::result::Result<Self::Connection, Self::Error> {
-        let env = &ENV.0;
-        Ok(ODBCConnection(
-            env.connect_with_connection_string(&self.connection_string)?,
-        ))
+        let (conn, env) = self.config.connect()?;
+        Ok(ODBCConnection(conn, env, None, false))
     }
 
-    fn is_valid(&self, _conn: &mut Self::Connection) -> std::result::Result<(), Self::Error> {
-        //TODO
+    fn is_valid(&self, conn: &mut Self::Connection) -> std::result::Result<(), Self::Error> {
+        if conn.3 {
+            conn.restore_client_info()?;
+            self.release(&mut conn.0)?;
+        }
+        conn.validate(self.config.validation_query.as_deref())?;
+        conn.3 = true;
         Ok(())
     }
 
-    fn has_broken(&self, _conn: &mut Self::Connection) -> bool {
-        //TODO
-        false
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        conn.has_broken()
+    }
+}
+
+impl ODBCConnectionManagerTx {
+    /// Creates a new `ODBCConnectionManagerTx`.
+    pub fn new<S: Into<String>>(connection_string: S) -> ODBCConnectionManagerTx {
+        ODBCConnectionManagerTx::with_config(PoolConfig::new(connection_string))
+    }
+
+    /// Creates a manager with the given settings
+    pub fn with_config(config: PoolConfig) -> ODBCConnectionManagerTx {
+        ODBCConnectionManagerTx {
+            config,
+            reset: None,
+        }
+    }
+
+    /// Like `ODBCConnectionManager::with_reset`. Whatever `reset` does is committed.
+    pub fn with_reset<F>(mut self, reset: F) -> ODBCConnectionManagerTx
+    where
+        F: Fn(&mut Connection<'static, safe::AutocommitOff>) -> HookResult + Send + Sync + 'static,
+    {
+        self.reset = Some(ConnectionHook(Box::new(reset)));
+        self
+    }
+
+    /// Rolls back uncommitted work and prepares a returned connection for the next user, see
+    /// `ODBCConnectionManager::release`
+    fn release(
+        &self,
+        conn: &mut Connection<'static, safe::AutocommitOff>,
+    ) -> std::result::Result<(), ODBCError> {
+        conn.rollback()?;
+        if self.config.reset_session {
+            conn.reset_session()?;
+            // Resetting the session enables autocommit again
+            conn.set_attr_u32(SQL_ATTR_AUTOCOMMIT, SQL_AUTOCOMMIT_OFF)?;
+            self.config.apply_client_info(conn)?;
+        }
+        if let Some(reset) = &self.reset {
+            (reset.0)(conn).map_err(ODBCError)?;
+        }
+        conn.commit()?;
+        Ok(())
     }
 }
 
@@ -154,23 +559,28 @@ impl r2d2::ManageConnection for ODBCConnectionManagerTx {
     type Error = ODBCError;
 
     fn connect(&self) -> std::result::Result<Self::Connection, Self::Error> {
-        let env = &ENV.0;
-        let conn = env.connect_with_connection_string(&self.connection_string)?;
+        let (conn, env) = self.config.connect()?;
         let conn_result = conn.disable_autocommit();
         match conn_result {
-            Ok(conn) => Ok(ODBCConnection(conn)),
+            Ok(conn) => Ok(ODBCConnection(conn, env, None, false)),
             _ => Err(ODBCError("Unable to use transactions".into())),
         }
     }
 
-    fn is_valid(&self, _conn: &mut Self::Connection) -> std::result::Result<(), Self::Error> {
-        //TODO
+    fn is_valid(&self, conn: &mut Self::Connection) -> std::result::Result<(), Self::Error> {
+        if conn.3 {
+            conn.restore_client_info()?;
+            self.release(&mut conn.0)?;
+        }
+        conn.validate(self.config.validation_query.as_deref())?;
+        conn.3 = true;
+        // Do not hand out a connection with the transaction opened by the validation query
+        conn.0.rollback()?;
         Ok(())
     }
 
-    fn has_broken(&self, _conn: &mut Self::Connection) -> bool {
-        //TODO
-        false
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        conn.has_broken()
     }
 }
 //Ends
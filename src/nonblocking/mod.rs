@@ -0,0 +1,845 @@
+//! Asynchronous connections for applications running on an async runtime like tokio. Enabled with
+//! the `async` feature.
+//!
+//! CLI calls block the calling thread, so they are executed on a `WorkerPool`, a bounded set of
+//! threads shared by many connections. The requests of a connection run one after another on
+//! whichever worker is free, results are passed back through channels. A worker is only occupied
+//! while a CLI call runs: a `RowStream` fetches its rows in batches, and the cursor stays with the
+//! connection in between, so slow consumers do not hold on to a thread. Dropping a pending future
+//! or an unfinished `RowStream` cancels the running statement with `SQLCancel`.
+//!
+//! `AsyncConnection::connect` uses a pool shared by the whole process, which has 8 threads. Use
+//! `AsyncConnection::connect_with_pool` to run connections on a pool of a different size.
+//!
+//! ```no_run
+//! use futures::StreamExt;
+//! use ibm_db::nonblocking::AsyncConnection;
+//! use ibm_db::ODBCEnv;
+//! use std::sync::Arc;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let env = Arc::new(ODBCEnv::new()?);
+//! let conn = AsyncConnection::connect(env, "DSN=sample;UID=db2inst1;PWD=secret").await?;
+//! let mut rows = conn.query("SELECT NAME FROM SYSIBM.SYSTABLES").await?;
+//! while let Some(row) = rows.next().await {
+//!     println!("{:?}", row?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+#[cfg(any(feature = "async-bb8", feature = "async-deadpool"))]
+mod pool;
+
+#[cfg(any(feature = "async-bb8", feature = "async-deadpool"))]
+pub use self::pool::AsyncConnectionManager;
+use super::{
+    ffi, Connection, Cursor, DiagnosticRecord, Error as DbError, Executed, Handle, HasResult,
+    ODBCEnv, ResultSetState::Data, ResultSetState::NoData, SqlDate, SqlTime, SqlTimestamp,
+    Statement,
+};
+use futures::channel::oneshot;
+use futures::{ready, Future, Stream};
+use odbc_safe::AutocommitOn;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+use std::thread;
+
+/// Value of a column. Integers, floating point numbers, binary data, dates and times keep their
+/// type, all other values, e.g. `DECIMAL` or `XML`, are converted to strings.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Double(f64),
+    Boolean(bool),
+    Text(String),
+    Binary(Vec<u8>),
+    Date(SqlDate),
+    Time(SqlTime),
+    Timestamp(SqlTimestamp),
+}
+
+/// Values of a row. `None` represents `NULL`.
+pub type Row = Vec<Option<Value>>;
+
+/// Number of rows fetched at once for a `RowStream`. The next batch is fetched while the current
+/// one is consumed.
+const ROW_BATCH: usize = 64;
+
+/// Number of threads of the pool used by `AsyncConnection::connect`
+const DEFAULT_WORKERS: usize = 8;
+
+/// Errors of the asynchronous API
+#[derive(Debug)]
+pub enum AsyncError {
+    /// An error of the blocking API, e.g. one reported by the CLI
+    Database(DbError),
+    /// Any other error, e.g. a failed data conversion
+    Other(String),
+    /// The connection has been closed, e.g. because a callback passed to
+    /// `AsyncConnection::with_connection` panicked
+    Disconnected,
+}
+
+impl fmt::Display for AsyncError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsyncError::Database(e) => e.fmt(f),
+            AsyncError::Other(message) => f.write_str(message),
+            AsyncError::Disconnected => f.write_str("Connection has been closed"),
+        }
+    }
+}
+
+impl Error for AsyncError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AsyncError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DbError> for AsyncError {
+    fn from(err: DbError) -> Self {
+        AsyncError::Database(err)
+    }
+}
+
+impl From<Box<dyn Error>> for AsyncError {
+    fn from(err: Box<dyn Error>) -> Self {
+        // Errors of the blocking API are not `Send`, so they are converted on the worker thread
+        let err = match err.downcast::<DbError>() {
+            Ok(err) => return AsyncError::Database(*err),
+            Err(err) => err,
+        };
+        match err.downcast::<DiagnosticRecord>() {
+            Ok(diag) => AsyncError::Database(DbError::from_records(vec![*diag])),
+            Err(err) => AsyncError::Other(err.to_string()),
+        }
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A bounded set of threads executing the blocking calls of `AsyncConnection`s. Clones share the
+/// threads, which terminate once all clones and the connections using the pool are dropped.
+#[derive(Clone)]
+pub struct WorkerPool {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl WorkerPool {
+    /// Starts a pool with `threads` worker threads, at least one
+    pub fn new(threads: usize) -> Result<WorkerPool, AsyncError> {
+        let (jobs, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..threads.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name("ibm_db-worker".to_string())
+                .spawn(move || run_worker(&receiver))
+                .map_err(|e| AsyncError::Other(e.to_string()))?;
+        }
+        Ok(WorkerPool { jobs })
+    }
+
+    /// The pool used by `AsyncConnection::connect`, started on first use
+    fn shared() -> Result<WorkerPool, AsyncError> {
+        static SHARED: Mutex<Option<WorkerPool>> = Mutex::new(None);
+        // The lock only guards the creation, a panic while holding it leaves nothing inconsistent
+        let mut shared = SHARED.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(pool) = &*shared {
+            return Ok(pool.clone());
+        }
+        let pool = WorkerPool::new(DEFAULT_WORKERS)?;
+        *shared = Some(pool.clone());
+        Ok(pool)
+    }
+
+    fn submit(&self, job: Job) -> Result<(), AsyncError> {
+        self.jobs.send(job).map_err(|_| AsyncError::Disconnected)
+    }
+}
+
+impl fmt::Debug for WorkerPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("WorkerPool")
+    }
+}
+
+fn run_worker(jobs: &Mutex<mpsc::Receiver<Job>>) {
+    loop {
+        // The lock is only held while waiting for the next job
+        let job = jobs.lock().unwrap_or_else(PoisonError::into_inner).recv();
+        match job {
+            Ok(job) => job(),
+            Err(_) => return,
+        }
+    }
+}
+
+/// A connection whose blocking calls are executed on a `WorkerPool`
+pub struct AsyncConnection {
+    shared: Arc<Shared>,
+    next_request: AtomicU64,
+}
+
+impl AsyncConnection {
+    /// Connects within `env`, running the blocking calls on the pool shared by all connections
+    /// opened with this function. The connection is closed once the `AsyncConnection` and all
+    /// its `RowStream`s are dropped and all pending requests are done.
+    pub async fn connect(
+        env: Arc<ODBCEnv>,
+        connection_string: &str,
+    ) -> Result<AsyncConnection, AsyncError> {
+        Self::connect_with_pool(env, connection_string, WorkerPool::shared()?).await
+    }
+
+    /// Like `connect`, but runs the blocking calls of the connection on `pool`
+    pub async fn connect_with_pool(
+        env: Arc<ODBCEnv>,
+        connection_string: &str,
+        pool: WorkerPool,
+    ) -> Result<AsyncConnection, AsyncError> {
+        let (reply, connected) = oneshot::channel();
+        let connection_string = connection_string.to_string();
+        pool.submit(Box::new(move || {
+            let session = match env.connect(&connection_string) {
+                Ok(conn) => Ok(Session {
+                    cursors: HashMap::new(),
+                    conn,
+                    _env: env,
+                }),
+                Err(e) => Err(AsyncError::from(e.0)),
+            };
+            // If nobody is waiting for the connection anymore, it is closed again right here
+            let _ = reply.send(session);
+        }))?;
+        let session = connected.await.map_err(|_| AsyncError::Disconnected)??;
+        Ok(AsyncConnection {
+            shared: Arc::new(Shared::new(pool, session)),
+            next_request: AtomicU64::new(0),
+        })
+    }
+
+    /// Executes a statement which does not return rows, e.g. an `INSERT`. Returns the number of
+    /// affected rows, or -1 if not available.
+    pub async fn execute(&self, sql: &str) -> Result<i64, AsyncError> {
+        let progress = Arc::new(Progress::default());
+        let (reply, response) = oneshot::channel();
+        self.shared.send(Command::Execute {
+            sql: sql.to_string(),
+            progress: progress.clone(),
+            reply,
+        })?;
+        let guard = CancelOnDrop::new(progress);
+        let result = response.await.map_err(|_| AsyncError::Disconnected)?;
+        guard.disarm();
+        result
+    }
+
+    /// Executes a query. The cursor is kept by the connection and the rows are fetched in batches
+    /// while they are consumed from the returned stream.
+    pub async fn query(&self, sql: &str) -> Result<RowStream, AsyncError> {
+        let request = self.next_request.fetch_add(1, Ordering::Relaxed);
+        let progress = Arc::new(Progress::default());
+        let (reply, response) = oneshot::channel();
+        self.shared.send(Command::Query {
+            request,
+            sql: sql.to_string(),
+            progress: progress.clone(),
+            reply,
+        })?;
+        // Closes the cursor if this future is dropped once the query has been executed
+        let mut stream = RowStream {
+            shared: self.shared.clone(),
+            request,
+            columns: Vec::new(),
+            rows: VecDeque::new(),
+            fetch: None,
+            exhausted: false,
+        };
+        let guard = CancelOnDrop::new(progress);
+        let columns = response.await.unwrap_or(Err(AsyncError::Disconnected));
+        guard.disarm();
+        match columns {
+            Ok(columns) if columns.is_empty() => stream.exhausted = true,
+            Ok(columns) => {
+                stream.columns = columns;
+                if let Err(e) = stream.request_batch() {
+                    stream.exhausted = true;
+                    return Err(e);
+                }
+            }
+            Err(e) => {
+                stream.exhausted = true;
+                return Err(e);
+            }
+        }
+        Ok(stream)
+    }
+
+    /// Runs `f` with the underlying connection on a worker thread, e.g. to change connection
+    /// attributes or to use parts of the blocking API not covered here. Statements created by
+    /// `f` have to be dropped before it returns.
+    pub async fn with_connection<T, F>(&self, f: F) -> Result<T, AsyncError>
+    where
+        F: FnOnce(&mut Connection<'static, AutocommitOn>) -> Result<T, AsyncError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (reply, response) = oneshot::channel();
+        self.shared.send(Command::Call(Box::new(move |conn| {
+            if !reply.is_canceled() {
+                let _ = reply.send(f(conn));
+            }
+        })))?;
+        response.await.map_err(|_| AsyncError::Disconnected)?
+    }
+
+    /// `true` if the connection has been closed, e.g. because a callback passed to
+    /// `with_connection` panicked. All further requests fail with `AsyncError::Disconnected`.
+    pub fn is_closed(&self) -> bool {
+        self.shared.lock().closed
+    }
+}
+
+/// Rows of a query executed with `AsyncConnection::query`. Dropping the stream before it is
+/// exhausted cancels the running fetch and closes the cursor.
+pub struct RowStream {
+    shared: Arc<Shared>,
+    request: u64,
+    columns: Vec<String>,
+    /// Rows of the last batch which have not been consumed yet
+    rows: VecDeque<Row>,
+    /// The batch being fetched, `None` once the last one has been received
+    fetch: Option<PendingBatch>,
+    /// `true` once the connection has closed the cursor
+    exhausted: bool,
+}
+
+impl RowStream {
+    /// Names of the columns of the result set
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    fn request_batch(&mut self) -> Result<(), AsyncError> {
+        let progress = Arc::new(Progress::default());
+        let (reply, response) = oneshot::channel();
+        self.shared.send(Command::Fetch {
+            request: self.request,
+            progress: progress.clone(),
+            reply,
+        })?;
+        self.fetch = Some(PendingBatch { progress, response });
+        Ok(())
+    }
+}
+
+impl Stream for RowStream {
+    type Item = Result<Row, AsyncError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(row) = this.rows.pop_front() {
+                return Poll::Ready(Some(Ok(row)));
+            }
+            let response = match &mut this.fetch {
+                Some(fetch) => &mut fetch.response,
+                None => return Poll::Ready(None),
+            };
+            let batch = ready!(Pin::new(response).poll(cx));
+            let batch = batch.unwrap_or(Err(AsyncError::Disconnected));
+            this.fetch = None;
+            match batch {
+                Ok(batch) => {
+                    this.rows.extend(batch.rows);
+                    if batch.last {
+                        this.exhausted = true;
+                    } else if let Err(e) = this.request_batch() {
+                        this.exhausted = true;
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                }
+                Err(e) => {
+                    // The connection closes the cursor of a failed fetch
+                    this.exhausted = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for RowStream {
+    fn drop(&mut self) {
+        if let Some(fetch) = &self.fetch {
+            fetch.progress.cancel();
+        }
+        if !self.exhausted {
+            let _ = self.shared.send(Command::Close {
+                request: self.request,
+            });
+        }
+    }
+}
+
+struct PendingBatch {
+    progress: Arc<Progress>,
+    response: oneshot::Receiver<Result<Batch, AsyncError>>,
+}
+
+/// Rows fetched for a `RowStream`
+struct Batch {
+    rows: Vec<Row>,
+    /// `true` if the result set has been fetched completely and the cursor is closed
+    last: bool,
+}
+
+enum Command {
+    Execute {
+        sql: String,
+        progress: Arc<Progress>,
+        reply: oneshot::Sender<Result<i64, AsyncError>>,
+    },
+    /// Executes a query and keeps its cursor for the `Fetch` commands of the same request
+    Query {
+        request: u64,
+        sql: String,
+        progress: Arc<Progress>,
+        reply: oneshot::Sender<Result<Vec<String>, AsyncError>>,
+    },
+    Fetch {
+        request: u64,
+        progress: Arc<Progress>,
+        reply: oneshot::Sender<Result<Batch, AsyncError>>,
+    },
+    /// Closes the cursor of a `RowStream` dropped before it has been exhausted
+    Close {
+        request: u64,
+    },
+    Call(Callback),
+}
+
+type Callback = Box<dyn FnOnce(&mut Connection<'static, AutocommitOn>) + Send>;
+
+/// State of a connection shared by the `AsyncConnection`, its `RowStream`s and the job running its
+/// commands on the pool
+struct Shared {
+    pool: WorkerPool,
+    state: Mutex<State>,
+}
+
+struct State {
+    queue: VecDeque<Command>,
+    /// `true` while a job running the queued commands is submitted to the pool
+    scheduled: bool,
+    /// Taken by the job while it runs
+    session: Option<Session>,
+    closed: bool,
+}
+
+impl Shared {
+    fn new(pool: WorkerPool, session: Session) -> Shared {
+        Shared {
+            pool,
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                scheduled: false,
+                session: Some(session),
+                closed: false,
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // The commands run without holding the lock, so a panic can not leave the state
+        // inconsistent
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Queues `command` and submits a job running it to the pool, unless one is already
+    /// submitted for this connection
+    fn send(self: &Arc<Self>, command: Command) -> Result<(), AsyncError> {
+        let mut state = self.lock();
+        if state.closed {
+            return Err(AsyncError::Disconnected);
+        }
+        state.queue.push_back(command);
+        if !state.scheduled {
+            let shared = self.clone();
+            if let Err(e) = self.pool.submit(Box::new(move || shared.run())) {
+                // Dropped after the lock, the command may own a `RowStream` of this connection
+                let command = state.queue.pop_back();
+                drop(state);
+                drop(command);
+                return Err(e);
+            }
+            state.scheduled = true;
+        }
+        Ok(())
+    }
+
+    /// Runs the queued commands one after another, until the queue is empty
+    fn run(&self) {
+        let session = self.lock().session.take();
+        let mut session = match session {
+            Some(session) => session,
+            None => return self.close(),
+        };
+        loop {
+            let command = {
+                let mut state = self.lock();
+                match state.queue.pop_front() {
+                    Some(command) => command,
+                    None => {
+                        state.session = Some(session);
+                        state.scheduled = false;
+                        return;
+                    }
+                }
+            };
+            if panic::catch_unwind(AssertUnwindSafe(|| session.run(command))).is_err() {
+                error!("Closing asynchronous connection after a panic");
+                return self.close();
+            }
+        }
+    }
+
+    /// Fails all queued and further requests. The session has been taken by the caller.
+    fn close(&self) {
+        let queue = {
+            let mut state = self.lock();
+            state.closed = true;
+            state.scheduled = false;
+            std::mem::take(&mut state.queue)
+        };
+        // Dropping the commands outside the lock, their replies fail with `Disconnected`
+        drop(queue);
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        // Disconnecting blocks, so the session is dropped on the pool
+        let state = self.state.get_mut().unwrap_or_else(PoisonError::into_inner);
+        if let Some(session) = state.session.take() {
+            let _ = self.pool.submit(Box::new(move || drop(session)));
+        }
+    }
+}
+
+/// The connection and the cursors of its `RowStream`s. Fields are dropped in declaration order,
+/// so the cursors are freed before the connection and the connection before the environment.
+struct Session {
+    cursors: HashMap<u64, OpenCursor>,
+    conn: Connection<'static, AutocommitOn>,
+    _env: Arc<ODBCEnv>,
+}
+
+// CLI handles can be used from any thread, the session is only used by one worker at a time
+unsafe impl Send for Session {}
+
+impl Session {
+    fn run(&mut self, command: Command) {
+        match command {
+            Command::Execute {
+                sql,
+                progress,
+                reply,
+            } => {
+                let _ = reply.send(self.execute(&progress, &sql));
+            }
+            Command::Query {
+                request,
+                sql,
+                progress,
+                reply,
+            } => {
+                let _ = reply.send(self.open_query(request, &progress, &sql));
+            }
+            Command::Fetch {
+                request,
+                progress,
+                reply,
+            } => {
+                let _ = reply.send(self.fetch(request, &progress));
+            }
+            Command::Close { request } => {
+                self.cursors.remove(&request);
+            }
+            Command::Call(f) => f(&mut self.conn),
+        }
+    }
+
+    fn execute(&self, progress: &Progress, sql: &str) -> Result<i64, AsyncError> {
+        let stmt = Statement::with_parent(&self.conn)?;
+        progress.start(unsafe { stmt.handle() })?;
+        let outcome = stmt.try_exec_direct(sql);
+        // Every branch still owns the statement, so it is not freed yet
+        progress.finish();
+        match outcome {
+            Ok(Data(stmt)) => Ok(stmt.affected_row_count()?),
+            Ok(NoData(stmt)) => Ok(stmt.affected_row_count()?),
+            Err((_, e)) => Err(e.into()),
+        }
+    }
+
+    /// Executes a query and keeps its cursor. Returns the column names, none if the statement
+    /// has no result set.
+    fn open_query(
+        &mut self,
+        request: u64,
+        progress: &Progress,
+        sql: &str,
+    ) -> Result<Vec<String>, AsyncError> {
+        let stmt = Statement::with_parent(&self.conn)?;
+        progress.start(unsafe { stmt.handle() })?;
+        let outcome = stmt.try_exec_direct(sql);
+        progress.finish();
+        let stmt = match outcome {
+            Ok(Data(stmt)) => stmt,
+            Ok(NoData(_)) => return Ok(Vec::new()),
+            Err((_, e)) => return Err(e.into()),
+        };
+        let columns = (1..=stmt.num_result_cols()?)
+            .map(|i| stmt.describe_col(i as u16))
+            .collect::<Result<Vec<_>, _>>()?;
+        let kinds = columns.iter().map(|c| Kind::of(c.data_type)).collect();
+        let names = columns.into_iter().map(|c| c.name).collect();
+        let stmt = unsafe { detach(stmt) };
+        self.cursors.insert(request, OpenCursor { stmt, kinds });
+        Ok(names)
+    }
+
+    /// Fetches the next batch of rows of a query, closing its cursor after the last batch or an
+    /// error
+    fn fetch(&mut self, request: u64, progress: &Progress) -> Result<Batch, AsyncError> {
+        let cursor = self
+            .cursors
+            .get_mut(&request)
+            .ok_or_else(|| AsyncError::Other("Cursor has been closed".to_string()))?;
+        progress.start(unsafe { cursor.stmt.handle() })?;
+        let batch = cursor.fetch_batch();
+        progress.finish();
+        if batch.as_ref().map_or(true, |batch| batch.last) {
+            self.cursors.remove(&request);
+        }
+        batch
+    }
+}
+
+/// Extends the lifetime of a statement to `'static`, so it can be kept in the session of the
+/// connection it has been allocated on. Sound as the session frees its cursors first.
+unsafe fn detach(
+    stmt: Statement<'_, '_, Executed, HasResult, AutocommitOn>,
+) -> Statement<'static, 'static, Executed, HasResult, AutocommitOn> {
+    std::mem::transmute::<
+        Statement<'_, '_, Executed, HasResult, AutocommitOn>,
+        Statement<'static, 'static, Executed, HasResult, AutocommitOn>,
+    >(stmt)
+}
+
+/// Result set of a query whose rows have not all been fetched yet
+struct OpenCursor {
+    stmt: Statement<'static, 'static, Executed, HasResult, AutocommitOn>,
+    kinds: Vec<Kind>,
+}
+
+impl OpenCursor {
+    fn fetch_batch(&mut self) -> Result<Batch, AsyncError> {
+        let mut rows = Vec::with_capacity(ROW_BATCH);
+        while rows.len() < ROW_BATCH {
+            match self.stmt.fetch()? {
+                Some(mut cursor) => rows.push(read_row(&mut cursor, &self.kinds)?),
+                None => return Ok(Batch { rows, last: true }),
+            }
+        }
+        Ok(Batch { rows, last: false })
+    }
+}
+
+fn read_row(
+    cursor: &mut Cursor<'_, '_, '_, Executed, AutocommitOn>,
+    kinds: &[Kind],
+) -> Result<Row, DbError> {
+    kinds
+        .iter()
+        .enumerate()
+        .map(|(i, kind)| kind.get_data(cursor, i as u16 + 1))
+        .collect()
+}
+
+/// How the values of a column are retrieved
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Integer,
+    Double,
+    Boolean,
+    Text,
+    Binary,
+    Date,
+    Time,
+    Timestamp,
+}
+
+impl Kind {
+    fn of(data_type: ffi::SqlDataType) -> Kind {
+        use crate::ffi::SqlDataType::*;
+        match data_type {
+            SQL_SMALLINT | SQL_INTEGER | SQL_EXT_BIGINT | SQL_EXT_TINYINT => Kind::Integer,
+            SQL_REAL | SQL_FLOAT | SQL_DOUBLE => Kind::Double,
+            SQL_EXT_BIT => Kind::Boolean,
+            SQL_EXT_BINARY | SQL_EXT_VARBINARY | SQL_EXT_LONGVARBINARY => Kind::Binary,
+            SQL_DATE => Kind::Date,
+            SQL_TIME => Kind::Time,
+            SQL_TIMESTAMP => Kind::Timestamp,
+            _ => Kind::Text,
+        }
+    }
+
+    fn get_data(
+        self,
+        cursor: &mut Cursor<'_, '_, '_, Executed, AutocommitOn>,
+        column: u16,
+    ) -> Result<Option<Value>, DbError> {
+        Ok(match self {
+            Kind::Integer => cursor.get_data::<i64>(column)?.map(Value::Integer),
+            Kind::Double => cursor.get_data::<f64>(column)?.map(Value::Double),
+            Kind::Boolean => cursor.get_data::<bool>(column)?.map(Value::Boolean),
+            Kind::Text => cursor.get_data::<String>(column)?.map(Value::Text),
+            Kind::Binary => cursor.get_data::<Vec<u8>>(column)?.map(Value::Binary),
+            Kind::Date => cursor.get_data::<SqlDate>(column)?.map(Value::Date),
+            Kind::Time => cursor.get_data::<SqlTime>(column)?.map(Value::Time),
+            Kind::Timestamp => cursor.get_data::<SqlTimestamp>(column)?.map(Value::Timestamp),
+        })
+    }
+}
+
+/// Progress of a request, shared by its future and the worker executing it. Dropping the future
+/// cancels the request: it is skipped if it has not started yet, and its statement is cancelled
+/// with `SQLCancel` while it runs. The worker marks the request finished before it frees the
+/// statement, cancelling happens under the same lock.
+#[derive(Default)]
+struct Progress(Mutex<Stage>);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Stage {
+    #[default]
+    Queued,
+    /// Running on the statement with the given handle
+    Running(usize),
+    Cancelled,
+    Finished,
+}
+
+impl Progress {
+    fn lock(&self) -> MutexGuard<'_, Stage> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Marks the request as running on `handle`. Fails if it has been cancelled before.
+    fn start(&self, handle: ffi::SQLHSTMT) -> Result<(), AsyncError> {
+        let mut stage = self.lock();
+        if *stage == Stage::Cancelled {
+            return Err(AsyncError::Other("Request has been cancelled".to_string()));
+        }
+        *stage = Stage::Running(handle as usize);
+        Ok(())
+    }
+
+    fn finish(&self) {
+        *self.lock() = Stage::Finished;
+    }
+
+    fn cancel(&self) {
+        let mut stage = self.lock();
+        match *stage {
+            Stage::Queued => *stage = Stage::Cancelled,
+            Stage::Running(handle) => match unsafe { ffi::SQLCancel(handle as ffi::SQLHSTMT) } {
+                ffi::SQL_SUCCESS | ffi::SQL_SUCCESS_WITH_INFO => (),
+                r => warn!("SQLCancel returned unexpected result: {:?}", r),
+            },
+            Stage::Cancelled | Stage::Finished => (),
+        }
+    }
+}
+
+/// Cancels a request if dropped before being disarmed
+struct CancelOnDrop {
+    progress: Arc<Progress>,
+    armed: bool,
+}
+
+impl CancelOnDrop {
+    fn new(progress: Arc<Progress>) -> CancelOnDrop {
+        CancelOnDrop {
+            progress,
+            armed: true,
+        }
+    }
+
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if self.armed {
+            self.progress.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn kind_of_column() {
+        assert_eq!(Kind::of(ffi::SqlDataType::SQL_EXT_BIGINT), Kind::Integer);
+        assert_eq!(Kind::of(ffi::SqlDataType::SQL_DOUBLE), Kind::Double);
+        assert_eq!(Kind::of(ffi::SqlDataType::SQL_EXT_VARBINARY), Kind::Binary);
+        assert_eq!(Kind::of(ffi::SqlDataType::SQL_TIMESTAMP), Kind::Timestamp);
+        // Decimals are kept as text, so no precision is lost
+        assert_eq!(Kind::of(ffi::SqlDataType::SQL_DECIMAL), Kind::Text);
+    }
+
+    #[test]
+    fn cancelled_request_is_skipped() {
+        let progress = Progress::default();
+        progress.cancel();
+        assert!(progress.start(ptr::null_mut()).is_err());
+    }
+
+    #[test]
+    fn cancel_after_finish_is_ignored() {
+        let progress = Progress::default();
+        progress.start(ptr::null_mut()).unwrap();
+        progress.finish();
+        progress.cancel();
+        assert_eq!(*progress.lock(), Stage::Finished);
+    }
+
+    #[test]
+    fn pool_runs_jobs() {
+        let pool = WorkerPool::new(2).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        for i in 0..4 {
+            let sender = sender.clone();
+            pool.submit(Box::new(move || sender.send(i).unwrap())).unwrap();
+        }
+        let mut received: Vec<i32> = receiver.iter().take(4).collect();
+        received.sort_unstable();
+        assert_eq!(received, vec![0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,187 @@
+//! Connection manager for the async connection pools bb8 (feature `async-bb8`) and deadpool
+//! (feature `async-deadpool`). Checking out a connection does not block the runtime, the pooled
+//! connections are `AsyncConnection`s.
+//!
+//! ```ignore
+//! use ibm_db::nonblocking::AsyncConnectionManager;
+//!
+//! let manager = AsyncConnectionManager::new("DSN=sample;UID=db2inst1;PWD=secret");
+//! let pool = bb8::Pool::builder().max_size(10).build(manager).await?;
+//! let conn = pool.get().await?;
+//! conn.execute("UPDATE STAFF SET SALARY = SALARY * 1.05").await?;
+//! ```
+use super::super::{
+    ClientInfo, Connection, ConnectionHook, HookResult, LazyEnv, ODBCEnv, Statement,
+    DEFAULT_VALIDATION_QUERY,
+};
+use super::{AsyncConnection, AsyncError, WorkerPool};
+use async_trait::async_trait;
+use odbc_safe::AutocommitOn;
+use std::sync::Arc;
+
+/// Creates and validates `AsyncConnection`s for bb8 and deadpool
+#[derive(Debug)]
+pub struct AsyncConnectionManager {
+    connection_string: String,
+    env: LazyEnv,
+    client_info: Option<ClientInfo>,
+    validation_query: Option<String>,
+    init: Option<Arc<ConnectionHook<AutocommitOn>>>,
+    workers: Option<WorkerPool>,
+}
+
+impl AsyncConnectionManager {
+    /// Creates a new `AsyncConnectionManager`.
+    pub fn new<S: Into<String>>(connection_string: S) -> AsyncConnectionManager {
+        AsyncConnectionManager {
+            connection_string: connection_string.into(),
+            env: LazyEnv::default(),
+            client_info: None,
+            validation_query: Some(DEFAULT_VALIDATION_QUERY.to_string()),
+            init: None,
+            workers: None,
+        }
+    }
+
+    /// Creates connections within `env` instead of an environment owned by the manager. The
+    /// environment can be shared with other managers, including the r2d2 ones.
+    pub fn with_env(mut self, env: Arc<ODBCEnv>) -> AsyncConnectionManager {
+        self.env = LazyEnv::from(env);
+        self
+    }
+
+    /// Creates the environment owned by the manager with the given encodings, see
+    /// `create_environment_v3_with_os_db_encoding`. Ignored if an environment is passed with
+    /// `with_env`.
+    pub fn with_os_db_encoding(
+        mut self,
+        os_encoding: &str,
+        db_encoding: &str,
+    ) -> AsyncConnectionManager {
+        self.env.encodings = Some((os_encoding.to_string(), db_encoding.to_string()));
+        self
+    }
+
+    /// Client information applied to every new connection
+    pub fn with_client_info(mut self, client_info: ClientInfo) -> AsyncConnectionManager {
+        self.client_info = Some(client_info);
+        self
+    }
+
+    /// Statement executed to check a connection before it is handed out again, `VALUES 1` by
+    /// default. Use e.g. `SELECT 1 FROM SYSIBM.SYSDUMMY1` for servers which do not support
+    /// `VALUES`.
+    pub fn with_validation_query<S: Into<String>>(mut self, query: S) -> AsyncConnectionManager {
+        self.validation_query = Some(query.into());
+        self
+    }
+
+    /// Only checks `SQL_ATTR_CONNECTION_DEAD` when validating, without a round trip to the server
+    pub fn without_validation_query(mut self) -> AsyncConnectionManager {
+        self.validation_query = None;
+        self
+    }
+
+    /// Runs `init` on a worker thread for every new connection, e.g. to set `CURRENT SCHEMA` or
+    /// the isolation level. A connection is discarded if `init` fails.
+    pub fn with_init<F>(mut self, init: F) -> AsyncConnectionManager
+    where
+        F: Fn(&mut Connection<'static, AutocommitOn>) -> HookResult + Send + Sync + 'static,
+    {
+        self.init = Some(Arc::new(ConnectionHook(Box::new(init))));
+        self
+    }
+
+    /// Runs the connections on `workers` instead of the pool shared by all connections, see
+    /// `AsyncConnection::connect_with_pool`
+    pub fn with_worker_pool(mut self, workers: WorkerPool) -> AsyncConnectionManager {
+        self.workers = Some(workers);
+        self
+    }
+
+    /// Connects and applies client information and the init hook
+    async fn open(&self) -> Result<AsyncConnection, AsyncError> {
+        let env = self.env.get().map_err(|e| AsyncError::from(e.0))?;
+        let conn = match &self.workers {
+            Some(workers) => {
+                AsyncConnection::connect_with_pool(env, &self.connection_string, workers.clone())
+                    .await?
+            }
+            None => AsyncConnection::connect(env, &self.connection_string).await?,
+        };
+        if self.client_info.is_some() || self.init.is_some() {
+            let client_info = self.client_info.clone();
+            let init = self.init.clone();
+            conn.with_connection(move |conn| {
+                if let Some(client_info) = &client_info {
+                    conn.set_client_info(client_info)?;
+                }
+                if let Some(init) = &init {
+                    (init.0)(conn)?;
+                }
+                Ok(())
+            })
+            .await?;
+        }
+        Ok(conn)
+    }
+
+    /// Fails if the connection is dead or the validation query can not be executed
+    async fn validate(&self, conn: &AsyncConnection) -> Result<(), AsyncError> {
+        let query = self.validation_query.clone();
+        conn.with_connection(move |conn| {
+            if conn.is_dead()? {
+                return Err(AsyncError::Other("Connection is dead".to_string()));
+            }
+            if let Some(query) = &query {
+                Statement::with_parent(&*conn).and_then(|stmt| stmt.exec_direct(query))?;
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "async-bb8")]
+#[async_trait]
+impl bb8::ManageConnection for AsyncConnectionManager {
+    type Connection = AsyncConnection;
+    type Error = AsyncError;
+
+    async fn connect(&self) -> Result<AsyncConnection, AsyncError> {
+        self.open().await
+    }
+
+    async fn is_valid(&self, conn: &mut AsyncConnection) -> Result<(), AsyncError> {
+        self.validate(conn).await
+    }
+
+    fn has_broken(&self, conn: &mut AsyncConnection) -> bool {
+        conn.is_closed()
+    }
+}
+
+#[cfg(feature = "async-deadpool")]
+#[async_trait]
+impl deadpool::managed::Manager for AsyncConnectionManager {
+    type Type = AsyncConnection;
+    type Error = AsyncError;
+
+    async fn create(&self) -> Result<AsyncConnection, AsyncError> {
+        self.open().await
+    }
+
+    async fn recycle(
+        &self,
+        conn: &mut AsyncConnection,
+    ) -> deadpool::managed::RecycleResult<AsyncError> {
+        if conn.is_closed() {
+            return Err(deadpool::managed::RecycleError::Message(
+                AsyncError::Disconnected.to_string(),
+            ));
+        }
+        self.validate(conn)
+            .await
+            .map_err(deadpool::managed::RecycleError::Backend)
+    }
+}
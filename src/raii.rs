@@ -37,7 +37,7 @@ impl<'p, T: OdbcObject> Drop for Raii<'p, T> {
                 let rec = self.get_diag_rec(1).unwrap_or_else(DiagnosticRecord::empty);
                 error!("Error freeing handle: {}", rec)
             }
-            _ => panic!("Unexepected return value of SQLFreeHandle"),
+            r => error!("SQLFreeHandle returned unexpected result: {:?}", r),
         }
     }
 }
@@ -64,7 +64,7 @@ impl<'p, T: OdbcObject> Raii<'p, T> {
                 parent: PhantomData,
             }),
             ffi::SQL_ERROR => Return::Error,
-            _ => panic!("SQLAllocHandle returned unexpected result"),
+            r => Return::unexpected("SQLAllocHandle", r),
         }
     }
 }
@@ -1,43 +1,194 @@
 //! Result types to enabling handling of ODBC Errors
-use super::safe;
-use super::{DiagnosticRecord, GetDiagRec};
+use super::diagnostics::{read_diag_rec, read_diag_rec_wide};
+use super::warnings::Warnings;
+use super::{ffi, safe};
+use super::{DiagnosticHeader, DiagnosticRecord, Error, Sqlca, TextEncoding};
+use std::task::Poll;
 
 /// Result type returned by most functions in this crate
-pub type Result<T> = std::result::Result<T, Box<DiagnosticRecord>>;
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Implemented by the objects passed to `Return::into_result`. Statements and connections keep
+/// the warnings of their calls and provide the SQLCA of failed ones, other objects only log
+/// warnings.
+pub(crate) trait CallDiagnostics {
+    fn warning_store(&self) -> Option<&Warnings> {
+        None
+    }
+
+    /// SQLCA of the last call, if it reported a non-zero SQLCODE
+    fn last_sqlca(&self) -> Option<Sqlca> {
+        None
+    }
+
+    /// Handle type and handle to read further diagnostic fields with `SQLGetDiagField`
+    fn diag_handle(&self) -> Option<(ffi::HandleType, ffi::SQLHANDLE)> {
+        None
+    }
+
+    /// Encoding the diagnostic messages are decoded with. Records are read with `SQLGetDiagRecW`
+    /// if it is wide and the object has a `diag_handle`.
+    fn encoding(&self) -> TextEncoding {
+        TextEncoding::default()
+    }
+}
+
+impl<T: CallDiagnostics + ?Sized> CallDiagnostics for &T {
+    fn warning_store(&self) -> Option<&Warnings> {
+        (**self).warning_store()
+    }
+
+    fn last_sqlca(&self) -> Option<Sqlca> {
+        (**self).last_sqlca()
+    }
+
+    fn diag_handle(&self) -> Option<(ffi::HandleType, ffi::SQLHANDLE)> {
+        (**self).diag_handle()
+    }
+
+    fn encoding(&self) -> TextEncoding {
+        (**self).encoding()
+    }
+}
+
+/// Reads all diagnostic records of the last call, including the row and column they refer to
+fn call_records<O>(odbc_object: &O) -> Vec<DiagnosticRecord>
+where
+    O: safe::Diagnostics + CallDiagnostics,
+{
+    let handle = odbc_object.diag_handle();
+    let encoding = odbc_object.encoding();
+    let read = |record_number| match handle {
+        Some((handle_type, handle)) if encoding.is_wide() => {
+            read_diag_rec_wide(handle_type, handle, record_number)
+        }
+        _ => read_diag_rec(odbc_object, record_number, encoding.os()),
+    };
+    let mut records = Vec::new();
+    while let Some(mut diag) = read(records.len() as i16 + 1) {
+        if let Some((handle_type, handle)) = handle {
+            diag.read_position(handle_type, handle, records.len() as i16 + 1);
+        }
+        records.push(diag);
+    }
+    records
+}
+
+/// Logs the warnings of a call which returned `SQL_SUCCESS_WITH_INFO` and keeps them on
+/// `odbc_object`. Fails if its warning policy promotes one of them to an error.
+fn keep_warnings<O: safe::Diagnostics + CallDiagnostics>(odbc_object: &O) -> Result<()> {
+    let records = call_records(odbc_object);
+    for diag in &records {
+        warn!("{}", diag);
+    }
+    match odbc_object.warning_store() {
+        Some(warnings) => warnings.keep(records),
+        None => Ok(()),
+    }
+}
+
+/// Like `error_from_diagnostics`, but also attaches the diagnostic header, the row and column
+/// numbers of the records and the SQLCA of `odbc_object`
+fn error_from_call<O: safe::Diagnostics + CallDiagnostics>(odbc_object: &O) -> Error {
+    let mut error = error_from_records(call_records(odbc_object));
+    if let Some((handle_type, handle)) = odbc_object.diag_handle() {
+        let encoding = odbc_object.encoding().os();
+        error = error.with_header(DiagnosticHeader::read(handle_type, handle, encoding));
+    }
+    match odbc_object.last_sqlca() {
+        Some(sqlca) => error.with_sqlca(sqlca),
+        None => error,
+    }
+}
+
+/// Collects all diagnostic records of a failed call into an `Error` and logs them
+pub(crate) fn error_from_diagnostics<D: safe::Diagnostics>(
+    odbc_object: &D,
+    encoding: TextEncoding,
+) -> Error {
+    let mut records = Vec::new();
+    while let Some(diag) = read_diag_rec(odbc_object, records.len() as i16 + 1, encoding.os()) {
+        records.push(diag);
+    }
+    error_from_records(records)
+}
+
+fn error_from_records(mut records: Vec<DiagnosticRecord>) -> Error {
+    if records.is_empty() {
+        records.push(DiagnosticRecord::empty());
+    }
+    for diag in &records {
+        error!("{}", diag);
+    }
+    Error::from_records(records)
+}
 
 #[must_use]
 pub enum Return<T> {
     Success(T),
     SuccessWithInfo(T),
     Error,
+    /// The function has been started asynchronously (`SQL_STILL_EXECUTING`) and has to be called
+    /// again until it returns something else.
+    StillExecuting,
+    /// The call failed without diagnostic records, e.g. because the function returned an
+    /// unexpected code or its result could not be converted
+    Failed(Error),
 }
 
 impl<T> Return<T> {
-    pub fn into_result<O: GetDiagRec>(self, odbc_object: &O) -> Result<T> {
+    /// Fails with `Error::UnexpectedReturn` for a return code `function` is not expected to
+    /// return
+    pub fn unexpected(function: &'static str, ret: ffi::SQLRETURN) -> Return<T> {
+        Return::Failed(Error::unexpected_return(function, ret))
+    }
+
+    /// Maps the plain `SQLSMALLINT` code returned by a CLI prototype of the crate root
+    pub(crate) fn from_cli(function: &'static str, ret: crate::SQLRETURN, value: T) -> Return<T> {
+        match i32::from(ret) {
+            r if r == crate::SQL_SUCCESS as i32 => Return::Success(value),
+            r if r == crate::SQL_SUCCESS_WITH_INFO as i32 => Return::SuccessWithInfo(value),
+            r if r == crate::SQL_STILL_EXECUTING as i32 => Return::StillExecuting,
+            crate::SQL_ERROR => Return::Error,
+            _ => Return::Failed(Error::UnexpectedReturn {
+                function,
+                code: ret,
+            }),
+        }
+    }
+
+    pub(crate) fn into_result<O>(self, odbc_object: &O) -> Result<T>
+    where
+        O: safe::Diagnostics + CallDiagnostics,
+    {
         match self {
             Return::Success(value) => Ok(value),
             Return::SuccessWithInfo(value) => {
-                let mut i = 1;
-                while let Some(diag) = odbc_object.get_diag_rec(i) {
-                    warn!("{}", diag);
-                    i += 1;
-                }
+                keep_warnings(odbc_object)?;
                 Ok(value)
             }
-            Return::Error => {
-                // Return the first record
-                let diag = odbc_object
-                    .get_diag_rec(1)
-                    .unwrap_or_else(DiagnosticRecord::empty);
+            Return::Error => Err(error_from_call(odbc_object)),
+            Return::StillExecuting => {
+                let diag = DiagnosticRecord::still_executing();
                 error!("{}", diag);
-                let mut i = 2;
-                // log the rest
-                while let Some(diag) = odbc_object.get_diag_rec(i) {
-                    error!("{}", diag);
-                    i += 1;
-                }
-                Err(diag.into())
+                Err(Error::from_records(vec![diag]))
             }
+            Return::Failed(error) => {
+                error!("{}", error);
+                Err(error)
+            }
+        }
+    }
+
+    /// Like `into_result`, but maps `StillExecuting` to `Poll::Pending`. Used by the polling
+    /// functions for asynchronous execution.
+    pub(crate) fn into_poll<O>(self, odbc_object: &O) -> Result<Poll<T>>
+    where
+        O: safe::Diagnostics + CallDiagnostics,
+    {
+        match self {
+            Return::StillExecuting => Ok(Poll::Pending),
+            ret => ret.into_result(odbc_object).map(Poll::Ready),
         }
     }
 }
@@ -45,95 +196,50 @@ impl<T> Return<T> {
 // temporary glue code to odbc-safe
 pub fn try_into_option<T, E, D>(ret: safe::ReturnOption<T, E>, handle: &D) -> Result<Option<T>>
 where
-    D: GetDiagRec,
+    D: safe::Diagnostics + CallDiagnostics,
 {
     match ret {
         safe::ReturnOption::Success(value) => Ok(Some(value)),
         safe::ReturnOption::Info(value) => {
-            let mut i = 1;
-            while let Some(diag) = handle.get_diag_rec(i) {
-                warn!("{}", diag);
-                i += 1;
-            }
+            keep_warnings(handle)?;
             Ok(Some(value))
         }
         safe::ReturnOption::NoData(_) => Ok(None),
-        safe::ReturnOption::Error(_) => {
-            // Return the first record
-            let diag = handle
-                .get_diag_rec(1)
-                .unwrap_or_else(DiagnosticRecord::empty);
-            error!("{}", diag);
-            let mut i = 2;
-            // log the rest
-            while let Some(diag) = handle.get_diag_rec(i) {
-                error!("{}", diag);
-                i += 1;
-            }
-            Err(diag.into())
-        }
+        safe::ReturnOption::Error(_) => Err(error_from_call(handle)),
     }
 }
 
 // temporary glue code to odbc-safe
-pub fn into_result<T, E>(ret: safe::Return<T, E>) -> Result<Box<T>>
+pub fn into_result<T, E>(ret: safe::Return<T, E>, encoding: TextEncoding) -> Result<Box<T>>
 where
-    T: GetDiagRec,
-    E: GetDiagRec,
+    T: safe::Diagnostics,
+    E: safe::Diagnostics,
 {
     match ret {
         safe::Return::Success(value) => Ok(value.into()),
         safe::Return::Info(value) => {
             let mut i = 1;
-            while let Some(diag) = value.get_diag_rec(i) {
+            while let Some(diag) = read_diag_rec(&value, i, encoding.os()) {
                 warn!("{}", diag);
                 i += 1;
             }
             Ok(value.into())
         }
-        safe::Return::Error(value) => {
-            // Return the first record
-            let diag = value
-                .get_diag_rec(1)
-                .unwrap_or_else(DiagnosticRecord::empty);
-            error!("{}", diag);
-            let mut i = 2;
-            // log the rest
-            while let Some(diag) = value.get_diag_rec(i) {
-                error!("{}", diag);
-                i += 1;
-            }
-            Err(diag.into())
-        }
+        safe::Return::Error(value) => Err(error_from_diagnostics(&value, encoding)),
     }
 }
 
 // temporary glue code to odbc-safe
 pub fn into_result_with<D, T, E>(diag: &D, ret: safe::Return<T, E>) -> Result<T>
 where
-    D: GetDiagRec,
+    D: safe::Diagnostics + CallDiagnostics,
 {
     match ret {
         safe::Return::Success(value) => Ok(value),
         safe::Return::Info(value) => {
-            let mut i = 1;
-            while let Some(diag_rec) = diag.get_diag_rec(i) {
-                warn!("{}", diag_rec);
-                i += 1;
-            }
+            keep_warnings(diag)?;
             Ok(value)
         }
-        safe::Return::Error(_) => {
-            // Return the first record
-            let diag_rec = diag.get_diag_rec(1).unwrap_or_else(DiagnosticRecord::empty);
-            error!("{}", diag_rec);
-            let mut i = 2;
-            // log the rest
-            while let Some(diag_rec) = diag.get_diag_rec(i) {
-                error!("{}", diag_rec);
-                i += 1;
-            }
-            Err(diag_rec.into())
-        }
+        safe::Return::Error(_) => Err(error_from_call(diag)),
     }
 }
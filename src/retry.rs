@@ -0,0 +1,226 @@
+//! Retrying operations which failed with transient errors like deadlocks
+use super::{Connection, Error, Result};
+use odbc_safe::AutocommitOn;
+use std::thread;
+use std::time::Duration;
+
+/// Decides which errors are retried, how often and how long to wait in between
+///
+/// By default the errors classified by `Error::is_transient` are retried, i.e. deadlocks, lock
+/// timeouts and client reroutes. An operation is attempted at most three times, waiting 50ms
+/// before the second attempt and doubling the delay for every further one.
+///
+/// ```no_run
+/// # use ibm_db::{create_environment_v3, RetryPolicy};
+/// # fn transfer() -> ibm_db::Result<()> {
+/// # let env = create_environment_v3().unwrap();
+/// let mut conn = env.connect_with_connection_string("DSN=sample")?;
+/// let policy = RetryPolicy::new().with_max_attempts(5);
+/// conn.transaction_with_retry(&policy, |tx| {
+///     let stmt = ibm_db::Statement::with_parent(&**tx)?;
+///     stmt.exec_direct("UPDATE ACCOUNT SET BALANCE = BALANCE - 10 WHERE ID = 1")?;
+///     Ok(())
+/// })
+/// .into_result()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    sqlcodes: Vec<i32>,
+    sqlstates: Vec<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(5),
+            sqlcodes: Vec::new(),
+            sqlstates: Vec::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates the default policy
+    pub fn new() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// Number of attempts including the first one. `1` disables retries.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Waits `initial` before the first retry and doubles the delay for every further one, up to
+    /// `max`
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> RetryPolicy {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Also retries errors with the given DB2 SQLCODE, e.g. `-904` for unavailable resources
+    pub fn retry_sqlcode(mut self, sqlcode: i32) -> RetryPolicy {
+        self.sqlcodes.push(sqlcode);
+        self
+    }
+
+    /// Also retries errors with the given SQLSTATE, e.g. `57011`
+    pub fn retry_sqlstate<S: Into<String>>(mut self, sqlstate: S) -> RetryPolicy {
+        self.sqlstates.push(sqlstate.into());
+        self
+    }
+
+    /// `true` if an operation failing with `error` is retried
+    pub fn is_transient(&self, error: &Error) -> bool {
+        if error.is_transient() {
+            return true;
+        }
+        let sqlcode = error.sqlcode();
+        let sqlstate = error.sqlstate();
+        self.sqlcodes.iter().any(|&code| sqlcode == Some(code))
+            || self
+                .sqlstates
+                .iter()
+                .any(|state| matches!(sqlstate, Some(s) if s == state.as_str()))
+    }
+
+    /// Delay before the given retry, starting at `1` for the second attempt
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+
+    /// Runs `f` until it succeeds, fails with an error which is not transient or the attempts are
+    /// exhausted. The last error is returned in the latter cases.
+    pub fn run<T, F>(&self, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+    {
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Err(error) if self.should_retry(&error, attempt) => {
+                    self.wait(&error, attempt);
+                    attempt += 1;
+                }
+                result => return self.finish(result, attempt),
+            }
+        }
+    }
+
+    /// Like `run`, but also retries if the connection has been lost (class `08`). In that case
+    /// `conn` is replaced with a new connection created by `connect` before the next attempt.
+    pub fn run_with_reconnect<'env, T, C, F>(
+        &self,
+        conn: &mut Connection<'env, AutocommitOn>,
+        mut connect: C,
+        mut f: F,
+    ) -> Result<T>
+    where
+        C: FnMut() -> Result<Connection<'env, AutocommitOn>>,
+        F: FnMut(&mut Connection<'env, AutocommitOn>) -> Result<T>,
+    {
+        let mut attempt = 1;
+        loop {
+            let error = match f(conn) {
+                Err(error) => error,
+                result => return self.finish(result, attempt),
+            };
+            // A failed probe is not proof of a lost connection, so it only counts if the driver
+            // reports the connection dead
+            let lost = error.is_connection_lost() || matches!(conn.is_dead(), Ok(true));
+            if attempt >= self.max_attempts || !(lost || self.is_transient(&error)) {
+                return self.finish(Err(error), attempt);
+            }
+            self.wait(&error, attempt);
+            attempt += 1;
+            if lost {
+                info!(
+                    "Reconnecting before attempt {} of {}",
+                    attempt, self.max_attempts
+                );
+                *conn = connect()?;
+            }
+        }
+    }
+
+    pub(crate) fn should_retry(&self, error: &Error, attempt: u32) -> bool {
+        attempt < self.max_attempts && self.is_transient(error)
+    }
+
+    pub(crate) fn wait(&self, error: &Error, attempt: u32) {
+        let delay = self.backoff(attempt);
+        warn!(
+            "Attempt {} of {} failed with a transient error, retrying in {:?}: {}",
+            attempt, self.max_attempts, delay, error
+        );
+        thread::sleep(delay);
+    }
+
+    pub(crate) fn finish<T>(&self, result: Result<T>, attempt: u32) -> Result<T> {
+        match &result {
+            Ok(_) if attempt > 1 => info!("Succeeded after {} attempts", attempt),
+            Err(error) if attempt > 1 => {
+                error!("Giving up after {} attempts: {}", attempt, error)
+            }
+            _ => (),
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff() {
+        let policy =
+            RetryPolicy::new().with_backoff(Duration::from_millis(10), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(10));
+        assert_eq!(policy.backoff(2), Duration::from_millis(20));
+        assert_eq!(policy.backoff(4), Duration::from_millis(80));
+        assert_eq!(policy.backoff(5), Duration::from_millis(100));
+        assert_eq!(policy.backoff(40), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn stops_at_max_attempts() {
+        let policy = RetryPolicy::new()
+            .with_max_attempts(3)
+            .with_backoff(Duration::from_millis(0), Duration::from_millis(0))
+            .retry_sqlstate("HY010");
+        let mut calls = 0;
+        let result: Result<()> = policy.run(|| {
+            calls += 1;
+            Err(Error::from_records(vec![
+                crate::DiagnosticRecord::still_executing(),
+            ]))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn does_not_retry_other_errors() {
+        let policy = RetryPolicy::new();
+        let mut calls = 0;
+        let result: Result<()> = policy.run(|| {
+            calls += 1;
+            Err(Error::other("invalid argument"))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
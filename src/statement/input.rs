@@ -1,10 +1,9 @@
 use super::types::EncodedValue;
 use super::types::OdbcType;
 use odbc_safe::AutocommitMode;
-use std::error::Error;
 use {
     super::super::ffi, super::super::Handle, super::super::Raii, super::super::Return,
-    super::super::Statement,
+    super::super::Statement, super::super::TextEncoding,
 };
 
 impl<'a, 'b, S, R, AC: AutocommitMode> Statement<'a, 'b, S, R, AC> {
@@ -39,26 +38,28 @@ impl<'a, 'b, S, R, AC: AutocommitMode> Statement<'a, 'b, S, R, AC> {
         mut self,
         parameter_index: u16,
         value: &'c T,
-    ) -> Result<Statement<'a, 'c, S, R, AC>, Box<dyn Error>>
+    ) -> crate::Result<Statement<'a, 'c, S, R, AC>>
     where
         T: OdbcType<'c>,
         T: ?Sized,
         'b: 'c,
     {
+        //the result of value_ptr is changed per calling.
+        //binding and saving must have the same value.
+        let enc_value = value.encoded_value(self.encoding);
+
         let ind = if value.value_ptr() == std::ptr::null::<Self>() as ffi::SQLPOINTER {
             ffi::SQL_NULL_DATA
+        } else if enc_value.has_value() {
+            enc_value.column_size() as ffi::SQLLEN
         } else {
             value.column_size() as ffi::SQLLEN
         };
 
         let ind_ptr = self.param_ind_buffers.alloc(parameter_index as usize, ind);
 
-        //the result of value_ptr is changed per calling.
-        //binding and saving must have the same value.
-        let enc_value = value.encoded_value();
-
         self.raii
-            .bind_input_parameter(parameter_index, value, ind_ptr, &enc_value)
+            .bind_input_parameter(parameter_index, value, ind_ptr, &enc_value, self.encoding)
             .into_result(&self)?;
 
         // save encoded value to avoid memory reuse.
@@ -71,11 +72,13 @@ impl<'a, 'b, S, R, AC: AutocommitMode> Statement<'a, 'b, S, R, AC> {
 
     /// Releasing all parameter buffers set by `bind_parameter`. This method consumes the statement
     /// and returns a new one those lifetime is no longer limited by the buffers bound.
-    pub fn reset_parameters(mut self) -> Result<Statement<'a, 'a, S, R, AC>, Box<dyn Error>> {
+    pub fn reset_parameters(mut self) -> crate::Result<Statement<'a, 'a, S, R, AC>> {
         self.param_ind_buffers.clear();
         self.encoded_values.clear();
         self.raii.reset_parameters().into_result(&self)?;
-        Ok(Statement::with_raii(self.raii))
+        let mut stmt = Statement::with_raii(self.raii, self.encoding);
+        stmt.warnings = self.warnings;
+        Ok(stmt)
     }
 }
 
@@ -86,6 +89,7 @@ impl<'p> Raii<'p, ffi::Stmt> {
         value: &'c T,
         str_len_or_ind_ptr: *mut ffi::SQLLEN,
         enc_value: &EncodedValue,
+        encoding: TextEncoding,
     ) -> Return<()>
     where
         T: OdbcType<'c>,
@@ -103,7 +107,7 @@ impl<'p> Raii<'p, ffi::Stmt> {
                 self.handle(),
                 parameter_index,
                 ffi::SQL_PARAM_INPUT,
-                T::c_data_type(),
+                T::c_data_type_for(encoding),
                 T::sql_data_type(),
                 column_size,
                 value.decimal_digits(),
@@ -115,7 +119,7 @@ impl<'p> Raii<'p, ffi::Stmt> {
             ffi::SQL_SUCCESS => Return::Success(()),
             ffi::SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(()),
             ffi::SQL_ERROR => Return::Error,
-            r => panic!("Unexpected return from SQLBindParameter: {:?}", r),
+            r => Return::unexpected("SQLBindParameter", r),
         }
     }
 
@@ -124,7 +128,7 @@ impl<'p> Raii<'p, ffi::Stmt> {
             ffi::SQL_SUCCESS => Return::Success(()),
             ffi::SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(()),
             ffi::SQL_ERROR => Return::Error,
-            r => panic!("SQLFreeStmt returned unexpected result: {:?}", r),
+            r => Return::unexpected("SQLFreeStmt", r),
         }
     }
 }
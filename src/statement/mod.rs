@@ -1,15 +1,28 @@
 #![allow(bare_trait_objects)]
 mod input;
 mod output;
+mod polling;
 mod prepare;
+mod privileges;
+mod type_info;
 mod types;
 pub use self::output::Output;
+pub use self::polling::{ExecDirect, Execute, Polled};
+pub use self::privileges::{ColumnPrivilege, TablePrivilege};
+pub use self::type_info::{Searchable, TypeInfo, ALL_TYPES};
 pub use self::types::OdbcType;
 pub use self::types::{EncodedValue, SqlDate, SqlSsTime2, SqlTime, SqlTimestamp};
 use super::ffi::SQLRETURN::*;
 use ffi::Nullable;
 use std::marker::PhantomData;
+use super::communication_area::{get_sqlca, last_sqlca};
+use super::result::CallDiagnostics;
+use super::warnings::Warnings;
 use {super::ffi, super::safe, super::Connection, super::Handle, super::Raii, super::Return};
+use std::ptr;
+use {super::DiagnosticHeader, super::DiagnosticRecord, super::Sqlca, super::WarningPolicy};
+use super::encoding::{from_wide, to_wide};
+use super::TextEncoding;
 
 // Allocate CHUNK_LEN elements at a time
 const CHUNK_LEN: usize = 64;
@@ -63,7 +76,6 @@ pub enum ResultSetState<'a, 'b, S, AC: AutocommitMode> {
     NoData(Statement<'a, 'b, S, NoResult, AC>),
 }
 use odbc_safe::AutocommitMode;
-use std::error::Error;
 use std::ptr::null_mut;
 pub use ResultSetState::*;
 
@@ -78,12 +90,16 @@ pub struct Statement<'a, 'b, S, R, AC: AutocommitMode> {
     param_ind_buffers: Chunks<ffi::SQLLEN>,
     // encoded values are saved to use its pointer.
     encoded_values: Vec<EncodedValue>,
+    warnings: Warnings,
+    encoding: TextEncoding,
 }
 
 /// Used to retrieve data from the fields of a query result
 pub struct Cursor<'s, 'a: 's, 'b: 's, S: 's, AC: AutocommitMode> {
     stmt: &'s mut Statement<'a, 'b, S, HasResult, AC>,
     buffer: Vec<u8>,
+    /// Column and buffer position of a `poll_get_data` call which is still executing
+    pending: Option<(u16, usize)>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -103,7 +119,7 @@ impl<'a, 'b, S, R, AC: AutocommitMode> Handle for Statement<'a, 'b, S, R, AC> {
 }
 
 impl<'a, 'b, S, R, AC: AutocommitMode> Statement<'a, 'b, S, R, AC> {
-    fn with_raii(raii: Raii<'a, ffi::Stmt>) -> Self {
+    fn with_raii(raii: Raii<'a, ffi::Stmt>, encoding: TextEncoding) -> Self {
         Statement {
             raii,
             autocommit_mode: PhantomData,
@@ -112,14 +128,77 @@ impl<'a, 'b, S, R, AC: AutocommitMode> Statement<'a, 'b, S, R, AC> {
             parameters: PhantomData,
             param_ind_buffers: Chunks::new(),
             encoded_values: Vec::new(),
+            warnings: Warnings::default(),
+            encoding,
         }
     }
+
+    /// Moves the handle and the kept warnings into a statement of another state
+    fn transit<S2, R2>(self) -> Statement<'a, 'b, S2, R2, AC> {
+        let mut stmt = Statement::with_raii(self.raii, self.encoding);
+        stmt.warnings = self.warnings;
+        stmt
+    }
+
+    /// Warnings reported by the calls on this statement since they have last been taken, in the
+    /// order they occurred. Only the 64 most recent ones are kept.
+    pub fn warnings(&self) -> Vec<DiagnosticRecord> {
+        self.warnings.get()
+    }
+
+    /// Returns the warnings like `warnings` and clears them
+    pub fn take_warnings(&self) -> Vec<DiagnosticRecord> {
+        self.warnings.take()
+    }
+
+    /// Sets the policy deciding which warnings are promoted to errors. Initially this is the
+    /// policy of the connection, see `Connection::set_warning_policy`.
+    pub fn set_warning_policy(&mut self, policy: WarningPolicy) {
+        self.warnings.set_policy(policy);
+    }
+
+    /// Encoding of this statement, the one of its connection at the time it has been allocated
+    pub fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+
+    /// SQL communication area of the last SQL statement executed with this statement handle
+    pub fn sqlca(&self) -> crate::Result<Sqlca> {
+        get_sqlca(ptr::null_mut(), unsafe { self.handle() }, self.encoding.os()).into_result(self)
+    }
+
+    /// Header fields of the diagnostic data of the last call on this statement, e.g. the row
+    /// count or the kind of statement executed
+    pub fn diagnostic_header(&self) -> DiagnosticHeader {
+        let handle = safe::Handle::handle(self);
+        DiagnosticHeader::read(ffi::SQL_HANDLE_STMT, handle, self.encoding.os())
+    }
+}
+
+impl<'a, 'b, S, R, AC: AutocommitMode> CallDiagnostics for Statement<'a, 'b, S, R, AC> {
+    fn warning_store(&self) -> Option<&Warnings> {
+        Some(&self.warnings)
+    }
+
+    fn last_sqlca(&self) -> Option<Sqlca> {
+        last_sqlca(ptr::null_mut(), unsafe { self.handle() }, self.encoding.os())
+    }
+
+    fn diag_handle(&self) -> Option<(ffi::HandleType, ffi::SQLHANDLE)> {
+        Some((ffi::SQL_HANDLE_STMT, safe::Handle::handle(self)))
+    }
+
+    fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
 }
 
 impl<'a, 'b, 'env, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC> {
-    pub fn with_parent(ds: &'a Connection<'env, AC>) -> Result<Self, Box<dyn Error>> {
+    pub fn with_parent(ds: &'a Connection<'env, AC>) -> crate::Result<Self> {
         let raii = Raii::with_parent(ds).into_result(ds)?;
-        Ok(Self::with_raii(raii))
+        let mut stmt = Self::with_raii(raii, ds.encoding());
+        stmt.warnings = Warnings::with_policy(ds.warning_policy().clone());
+        Ok(stmt)
     }
 
     pub fn affected_row_count(&self) -> crate::Result<i64> {
@@ -132,7 +211,7 @@ impl<'a, 'b, 'env, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC
         schema_name: &str,
         table_name: &str,
         table_type: &str,
-    ) -> Result<Statement<'a, 'b, Executed, HasResult, AC>, Box<dyn Error>> {
+    ) -> crate::Result<Statement<'a, 'b, Executed, HasResult, AC>> {
         self.tables_str(catalog_name, schema_name, table_name, table_type)
     }
 
@@ -142,7 +221,7 @@ impl<'a, 'b, 'env, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC
         schema_name: &str,
         table_name: &str,
         table_type: &str,
-    ) -> Result<Statement<'a, 'b, Executed, HasResult, AC>, Box<dyn Error>> {
+    ) -> crate::Result<Statement<'a, 'b, Executed, HasResult, AC>> {
         self.tables_opt_str(
             Option::Some(catalog_name),
             Option::Some(schema_name),
@@ -157,11 +236,11 @@ impl<'a, 'b, 'env, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC
         schema_name: Option<&str>,
         table_name: Option<&str>,
         table_type: &str,
-    ) -> Result<Statement<'a, 'b, Executed, HasResult, AC>, Box<dyn Error>> {
+    ) -> crate::Result<Statement<'a, 'b, Executed, HasResult, AC>> {
         self.raii
             .tables(catalog_name, schema_name, table_name, table_type)
             .into_result(&self)?;
-        Ok(Statement::with_raii(self.raii))
+        Ok(self.transit())
     }
 
     /// Executes a preparable statement, using the current values of the parameter marker variables
@@ -171,16 +250,45 @@ impl<'a, 'b, 'env, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC
     pub fn exec_direct(
         mut self,
         statement_text: &str,
-    ) -> Result<ResultSetState<'a, 'b, Executed, AC>, Box<dyn Error>> {
-        if self.raii.exec_direct(statement_text).into_result(&self)? {
+    ) -> crate::Result<ResultSetState<'a, 'b, Executed, AC>> {
+        if self
+            .raii
+            .exec_direct(statement_text, self.encoding)
+            .into_result(&self)?
+        {
             let num_cols = self.raii.num_result_cols().into_result(&self)?;
             if num_cols > 0 {
-                Ok(ResultSetState::Data(Statement::with_raii(self.raii)))
+                Ok(ResultSetState::Data(self.transit()))
             } else {
-                Ok(ResultSetState::NoData(Statement::with_raii(self.raii)))
+                Ok(ResultSetState::NoData(self.transit()))
             }
         } else {
-            Ok(ResultSetState::NoData(Statement::with_raii(self.raii)))
+            Ok(ResultSetState::NoData(self.transit()))
+        }
+    }
+
+    /// Like `exec_direct`, but hands the statement back if the execution fails instead of freeing
+    /// it. Used where the statement handle is shared with other threads, e.g. to cancel it, and
+    /// must not be freed before they are told so.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn try_exec_direct(
+        mut self,
+        statement_text: &str,
+    ) -> std::result::Result<ResultSetState<'a, 'b, Executed, AC>, (Self, crate::Error)> {
+        match self
+            .raii
+            .exec_direct(statement_text, self.encoding)
+            .into_result(&self)
+        {
+            Ok(true) => match self.raii.num_result_cols().into_result(&self) {
+                Ok(num_cols) if num_cols > 0 => {
+                    Ok(ResultSetState::Data(self.transit()))
+                }
+                Ok(_) => Ok(ResultSetState::NoData(self.transit())),
+                Err(diag) => Err((self, diag)),
+            },
+            Ok(false) => Ok(ResultSetState::NoData(self.transit())),
+            Err(diag) => Err((self, diag)),
         }
     }
 
@@ -191,16 +299,16 @@ impl<'a, 'b, 'env, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC
     pub fn exec_direct_bytes(
         mut self,
         bytes: &[u8],
-    ) -> Result<ResultSetState<'a, 'b, Executed, AC>, Box<dyn Error>> {
+    ) -> crate::Result<ResultSetState<'a, 'b, Executed, AC>> {
         if self.raii.exec_direct_bytes(bytes).into_result(&self)? {
             let num_cols = self.raii.num_result_cols().into_result(&self)?;
             if num_cols > 0 {
-                Ok(ResultSetState::Data(Statement::with_raii(self.raii)))
+                Ok(ResultSetState::Data(self.transit()))
             } else {
-                Ok(ResultSetState::NoData(Statement::with_raii(self.raii)))
+                Ok(ResultSetState::NoData(self.transit()))
             }
         } else {
-            Ok(ResultSetState::NoData(Statement::with_raii(self.raii)))
+            Ok(ResultSetState::NoData(self.transit()))
         }
     }
 }
@@ -220,15 +328,16 @@ impl<'a, 'b, S, AC: AutocommitMode> Statement<'a, 'b, S, HasResult, AC> {
 
     /// Returns description struct for result set column with a given index. Note: indexing is starting from 1.
     pub fn describe_col(&self, idx: u16) -> crate::Result<ColumnDescriptor> {
-        self.raii.describe_col(idx).into_result(self)
+        self.raii.describe_col(idx, self.encoding).into_result(self)
     }
 
     /// Fetches the next rowset of data from the result set and returns data for all bound columns.
-    pub fn fetch<'s>(&'s mut self) -> Result<Option<Cursor<'s, 'a, 'b, S, AC>>, Box<dyn Error>> {
+    pub fn fetch<'s>(&'s mut self) -> crate::Result<Option<Cursor<'s, 'a, 'b, S, AC>>> {
         if self.raii.fetch().into_result(self)? {
             Ok(Some(Cursor {
                 stmt: self,
                 buffer: vec![0; 512],
+                pending: None,
             }))
         } else {
             Ok(None)
@@ -262,24 +371,25 @@ impl<'a, 'b, S, AC: AutocommitMode> Statement<'a, 'b, S, HasResult, AC> {
     /// # Ok(())
     /// # };
     /// ```
-    pub fn close_cursor(mut self) -> Result<Statement<'a, 'b, S, NoResult, AC>, Box<dyn Error>> {
+    pub fn close_cursor(mut self) -> crate::Result<Statement<'a, 'b, S, NoResult, AC>> {
         self.raii.close_cursor().into_result(&self)?;
-        Ok(Statement::with_raii(self.raii))
+        Ok(self.transit())
     }
 }
 
 impl<'a, 'b, 'c, S, AC: AutocommitMode> Cursor<'a, 'b, 'c, S, AC> {
     /// Retrieves data for a single column in the result set
     ///
-    /// ## Panics
-    ///
-    /// If you try to convert to `&str` but the data can't be converted
-    /// without allocating an intermediate buffer.
+    /// Fails with `Error::Conversion` if you try to convert to `&str` but the data can't be
+    /// converted without allocating an intermediate buffer, which is always the case if the
+    /// statement uses the wide functions.
     pub fn get_data<'d, T>(&'d mut self, col_or_param_num: u16) -> crate::Result<Option<T>>
     where
         T: Output<'d>,
     {
-        T::get_data(&mut self.stmt.raii, col_or_param_num, &mut self.buffer).into_result(self.stmt)
+        let encoding = self.stmt.encoding;
+        T::get_data(&mut self.stmt.raii, col_or_param_num, &mut self.buffer, encoding)
+            .into_result(self.stmt)
     }
 }
 
@@ -291,7 +401,8 @@ impl<'p> Raii<'p, ffi::Stmt> {
                 SQL_SUCCESS => Return::Success(count),
                 SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(count),
                 SQL_ERROR => Return::Error,
-                r => panic!("SQLRowCount returned unexpected result: {:?}", r),
+                SQL_STILL_EXECUTING => Return::StillExecuting,
+                r => Return::unexpected("SQLRowCount", r),
             }
         }
     }
@@ -303,105 +414,113 @@ impl<'p> Raii<'p, ffi::Stmt> {
                 SQL_SUCCESS => Return::Success(num_cols),
                 SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(num_cols),
                 SQL_ERROR => Return::Error,
-                r => panic!("SQLNumResultCols returned unexpected result: {:?}", r),
+                SQL_STILL_EXECUTING => Return::StillExecuting,
+                r => Return::unexpected("SQLNumResultCols", r),
             }
         }
     }
 
-    fn describe_col(&self, idx: u16) -> Return<ColumnDescriptor> {
-        let mut name_buffer: [u8; 512] = [0; 512];
+    fn describe_col(&self, idx: u16, encoding: TextEncoding) -> Return<ColumnDescriptor> {
         let mut name_length: ffi::SQLSMALLINT = 0;
         let mut data_type: ffi::SqlDataType = ffi::SqlDataType::SQL_UNKNOWN_TYPE;
         let mut column_size: ffi::SQLULEN = 0;
         let mut decimal_digits: ffi::SQLSMALLINT = 0;
         let mut nullable: Nullable = Nullable::SQL_NULLABLE_UNKNOWN;
-        unsafe {
-            match ffi::SQLDescribeCol(
-                self.handle(),
-                idx,
-                name_buffer.as_mut_ptr(),
-                name_buffer.len() as ffi::SQLSMALLINT,
-                &mut name_length as *mut ffi::SQLSMALLINT,
-                &mut data_type as *mut ffi::SqlDataType,
-                &mut column_size as *mut ffi::SQLULEN,
-                &mut decimal_digits as *mut ffi::SQLSMALLINT,
-                &mut nullable as *mut ffi::Nullable,
-            ) {
-                SQL_SUCCESS => Return::Success(ColumnDescriptor {
-                    name: super::environment::DB_ENCODING
-                        .decode(&name_buffer[..(name_length as usize)])
-                        .0
-                        .to_string(),
-                    data_type,
-                    column_size: if column_size == 0 {
-                        None
-                    } else {
-                        Some(column_size)
-                    },
-                    decimal_digits: if decimal_digits == 0 {
-                        None
-                    } else {
-                        Some(decimal_digits as u16)
-                    },
-                    nullable: match nullable {
-                        Nullable::SQL_NULLABLE_UNKNOWN => None,
-                        Nullable::SQL_NULLABLE => Some(true),
-                        Nullable::SQL_NO_NULLS => Some(false),
-                    },
-                }),
-                SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(ColumnDescriptor {
-                    name: super::environment::DB_ENCODING
-                        .decode(&name_buffer[..(name_length as usize)])
-                        .0
-                        .to_string(),
-                    data_type,
-                    column_size: if column_size == 0 {
-                        None
-                    } else {
-                        Some(column_size)
-                    },
-                    decimal_digits: if decimal_digits == 0 {
-                        None
-                    } else {
-                        Some(decimal_digits as u16)
-                    },
-                    nullable: match nullable {
-                        Nullable::SQL_NULLABLE_UNKNOWN => None,
-                        Nullable::SQL_NULLABLE => Some(true),
-                        Nullable::SQL_NO_NULLS => Some(false),
-                    },
-                }),
-                SQL_ERROR => Return::Error,
-                r => panic!("SQLDescribeCol returned unexpected result: {:?}", r),
-            }
+        let (ret, name) = if encoding.is_wide() {
+            let mut name_buffer: [u16; 512] = [0; 512];
+            let ret = unsafe {
+                ffi::SQLDescribeColW(
+                    self.handle(),
+                    idx,
+                    name_buffer.as_mut_ptr(),
+                    name_buffer.len() as ffi::SQLSMALLINT,
+                    &mut name_length as *mut ffi::SQLSMALLINT,
+                    &mut data_type as *mut ffi::SqlDataType,
+                    &mut column_size as *mut ffi::SQLULEN,
+                    &mut decimal_digits as *mut ffi::SQLSMALLINT,
+                    &mut nullable as *mut ffi::Nullable,
+                )
+            };
+            let length = (name_length.max(0) as usize).min(name_buffer.len());
+            (ret, from_wide(&name_buffer[..length]))
+        } else {
+            let mut name_buffer: [u8; 512] = [0; 512];
+            let ret = unsafe {
+                ffi::SQLDescribeCol(
+                    self.handle(),
+                    idx,
+                    name_buffer.as_mut_ptr(),
+                    name_buffer.len() as ffi::SQLSMALLINT,
+                    &mut name_length as *mut ffi::SQLSMALLINT,
+                    &mut data_type as *mut ffi::SqlDataType,
+                    &mut column_size as *mut ffi::SQLULEN,
+                    &mut decimal_digits as *mut ffi::SQLSMALLINT,
+                    &mut nullable as *mut ffi::Nullable,
+                )
+            };
+            let length = (name_length.max(0) as usize).min(name_buffer.len());
+            (ret, encoding.db().decode(&name_buffer[..length]).0.into_owned())
+        };
+        let descriptor = || ColumnDescriptor {
+            name,
+            data_type,
+            column_size: if column_size == 0 {
+                None
+            } else {
+                Some(column_size)
+            },
+            decimal_digits: if decimal_digits == 0 {
+                None
+            } else {
+                Some(decimal_digits as u16)
+            },
+            nullable: match nullable {
+                Nullable::SQL_NULLABLE_UNKNOWN => None,
+                Nullable::SQL_NULLABLE => Some(true),
+                Nullable::SQL_NO_NULLS => Some(false),
+            },
+        };
+        match ret {
+            SQL_SUCCESS => Return::Success(descriptor()),
+            SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(descriptor()),
+            SQL_ERROR => Return::Error,
+            SQL_STILL_EXECUTING => Return::StillExecuting,
+            r => Return::unexpected("SQLDescribeCol", r),
         }
     }
 
-    fn exec_direct(&mut self, statement_text: &str) -> Return<bool> {
-        let bytes = unsafe { crate::environment::DB_ENCODING }
-            .encode(statement_text)
-            .0;
-
-        let length = bytes.len();
-        if length > ffi::SQLINTEGER::max_value() as usize {
-            panic!("Statement text too long");
-        }
-        match unsafe {
-            ffi::SQLExecDirect(self.handle(), bytes.as_ptr(), length as ffi::SQLINTEGER)
-        } {
+    fn exec_direct(&mut self, statement_text: &str, encoding: TextEncoding) -> Return<bool> {
+        let ret = if encoding.is_wide() {
+            let text = to_wide(statement_text);
+            if text.len() > ffi::SQLINTEGER::MAX as usize {
+                return Return::Failed(crate::Error::other("Statement text too long"));
+            }
+            unsafe {
+                ffi::SQLExecDirectW(self.handle(), text.as_ptr(), text.len() as ffi::SQLINTEGER)
+            }
+        } else {
+            let bytes = encoding.db().encode(statement_text).0;
+            if bytes.len() > ffi::SQLINTEGER::MAX as usize {
+                return Return::Failed(crate::Error::other("Statement text too long"));
+            }
+            unsafe {
+                ffi::SQLExecDirect(self.handle(), bytes.as_ptr(), bytes.len() as ffi::SQLINTEGER)
+            }
+        };
+        match ret {
             ffi::SQL_SUCCESS => Return::Success(true),
             ffi::SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(true),
             ffi::SQL_ERROR => Return::Error,
-            ffi::SQL_NEED_DATA => panic!("SQLExecDirec returned SQL_NEED_DATA"),
             ffi::SQL_NO_DATA => Return::Success(false),
-            r => panic!("SQLExecDirect returned unexpected result: {:?}", r),
+            ffi::SQL_STILL_EXECUTING => Return::StillExecuting,
+            r => Return::unexpected("SQLExecDirect", r),
         }
     }
 
     fn exec_direct_bytes(&mut self, bytes: &[u8]) -> Return<bool> {
         let length = bytes.len();
         if length > ffi::SQLINTEGER::max_value() as usize {
-            panic!("Statement text too long");
+            return Return::Failed(crate::Error::other("Statement text too long"));
         }
         match unsafe {
             ffi::SQLExecDirect(self.handle(), bytes.as_ptr(), length as ffi::SQLINTEGER)
@@ -409,9 +528,9 @@ impl<'p> Raii<'p, ffi::Stmt> {
             ffi::SQL_SUCCESS => Return::Success(true),
             ffi::SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(true),
             ffi::SQL_ERROR => Return::Error,
-            ffi::SQL_NEED_DATA => panic!("SQLExecDirec returned SQL_NEED_DATA"),
             ffi::SQL_NO_DATA => Return::Success(false),
-            r => panic!("SQLExecDirect returned unexpected result: {:?}", r),
+            ffi::SQL_STILL_EXECUTING => Return::StillExecuting,
+            r => Return::unexpected("SQLExecDirect", r),
         }
     }
 
@@ -422,7 +541,8 @@ impl<'p> Raii<'p, ffi::Stmt> {
             ffi::SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(true),
             ffi::SQL_ERROR => Return::Error,
             ffi::SQL_NO_DATA => Return::Success(false),
-            r => panic!("SQLFetch returned unexpected result: {:?}", r),
+            ffi::SQL_STILL_EXECUTING => Return::StillExecuting,
+            r => Return::unexpected("SQLFetch", r),
         }
     }
 
@@ -471,7 +591,8 @@ impl<'p> Raii<'p, ffi::Stmt> {
                 SQL_SUCCESS => Return::Success(()),
                 SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(()),
                 SQL_ERROR => Return::Error,
-                r => panic!("SQLTables returned: {:?}", r),
+                SQL_STILL_EXECUTING => Return::StillExecuting,
+                r => Return::unexpected("SQLTables", r),
             }
         }
     }
@@ -482,7 +603,7 @@ impl<'p> Raii<'p, ffi::Stmt> {
                 ffi::SQL_SUCCESS => Return::Success(()),
                 ffi::SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(()),
                 ffi::SQL_ERROR => Return::Error,
-                r => panic!("unexpected return value from SQLCloseCursor: {:?}", r),
+                r => Return::unexpected("SQLCloseCursor", r),
             }
         }
     }
@@ -1,6 +1,7 @@
 use super::super::raii::Raii;
 use super::types::OdbcType;
-use {super::super::ffi, super::super::Handle, super::super::Return};
+use {super::super::ffi, super::super::Error, super::super::Handle, super::super::Return};
+use super::super::TextEncoding;
 
 /// Indicates that a type can be retrieved using `Cursor::get_data`
 pub unsafe trait Output<'a>: Sized {
@@ -8,6 +9,7 @@ pub unsafe trait Output<'a>: Sized {
         stmt: &mut Raii<ffi::Stmt>,
         col_or_param_num: u16,
         buffer: &'a mut Vec<u8>,
+        encoding: TextEncoding,
     ) -> Return<Option<Self>>;
 }
 
@@ -19,8 +21,9 @@ where
         stmt: &mut Raii<ffi::Stmt>,
         col_or_param_num: u16,
         buffer: &'a mut Vec<u8>,
+        encoding: TextEncoding,
     ) -> Return<Option<Self>> {
-        stmt.get_data(col_or_param_num, buffer)
+        stmt.get_data(col_or_param_num, buffer, encoding)
     }
 }
 
@@ -29,53 +32,83 @@ impl<'p> Raii<'p, ffi::Stmt> {
         &mut self,
         col_or_param_num: u16,
         buffer: &'a mut Vec<u8>,
+        encoding: TextEncoding,
     ) -> Return<Option<T>>
     where
         T: OdbcType<'a>,
     {
-        self.get_partial_data(col_or_param_num, buffer, 0)
+        self.get_data_from(col_or_param_num, buffer, &mut 0, encoding)
     }
 
-    fn get_partial_data<'a, T>(
+    /// Like `get_data`, but starts writing at `start_pos`, which has to be the value left by a
+    /// previous call returning `StillExecuting` for the same column, or `0`.
+    pub(crate) fn get_data_from<'a, T>(
         &mut self,
         col_or_param_num: u16,
         buffer: &'a mut Vec<u8>,
-        start_pos: usize,
+        start_pos: &mut usize,
+        encoding: TextEncoding,
     ) -> Return<Option<T>>
     where
         T: OdbcType<'a>,
     {
-        if buffer.len() - start_pos == 0 {
-            panic!("buffer length may not be zero");
+        let c_data_type = T::c_data_type_for(encoding);
+        read_chunks(buffer, start_pos, encoding, |chunk| {
+            let mut indicator: ffi::SQLLEN = 0;
+            let result = unsafe {
+                ffi::SQLGetData(
+                    self.handle(),
+                    col_or_param_num,
+                    c_data_type,
+                    chunk.as_mut_ptr() as ffi::SQLPOINTER,
+                    chunk.len() as ffi::SQLLEN,
+                    &mut indicator as *mut ffi::SQLLEN,
+                )
+            };
+            (result, indicator)
+        })
+    }
+}
+
+/// Reads a value with `get_chunk`, which calls `SQLGetData` for the given part of `buffer` and
+/// returns its result and the length indicator. Whenever the value is truncated the buffer grows
+/// and the next chunk is appended to the data read so far. `start_pos` always holds the position
+/// of the chunk being read, so a read interrupted by `SQL_STILL_EXECUTING` can be resumed.
+fn read_chunks<'a, T, F>(
+    buffer: &'a mut Vec<u8>,
+    start_pos: &mut usize,
+    encoding: TextEncoding,
+    mut get_chunk: F,
+) -> Return<Option<T>>
+where
+    T: OdbcType<'a>,
+    F: FnMut(&mut [u8]) -> (ffi::SQLRETURN, ffi::SQLLEN),
+{
+    loop {
+        if buffer.len() - *start_pos == 0 {
+            return Return::Failed(Error::other("buffer length may not be zero"));
         }
-        if buffer.len() - start_pos > ffi::SQLLEN::max_value() as usize {
-            panic!("buffer is larger than {} bytes", ffi::SQLLEN::max_value());
+        if buffer.len() - *start_pos > ffi::SQLLEN::MAX as usize {
+            return Return::Failed(Error::other(format!(
+                "buffer is larger than {} bytes",
+                ffi::SQLLEN::MAX
+            )));
         }
-        let mut indicator: ffi::SQLLEN = 0;
-        // Get buffer length...
-        let result = unsafe {
-            ffi::SQLGetData(
-                self.handle(),
-                col_or_param_num,
-                T::c_data_type(),
-                buffer.as_mut_ptr().add(start_pos) as ffi::SQLPOINTER,
-                (buffer.len() - start_pos) as ffi::SQLLEN,
-                &mut indicator as *mut ffi::SQLLEN,
-            )
-        };
+        let (result, indicator) = get_chunk(&mut buffer[*start_pos..]);
         match result {
             ffi::SQL_SUCCESS => {
                 if indicator == ffi::SQL_NULL_DATA {
-                    Return::Success(None)
-                } else {
-                    //assert!(start_pos + indicator as usize <= buffer.len(), "no more data but indicatior outside of data buffer");
-                    if start_pos + indicator as usize >= buffer.len() {
-                        Return::Success(None)
-                    } else {
-                        let slice = &buffer[..(start_pos + indicator as usize)];
-                        Return::Success(Some(T::convert(slice)))
-                    }
+                    return Return::Success(None);
                 }
+                //assert!(start_pos + indicator as usize <= buffer.len(), "no more data but indicatior outside of data buffer");
+                if *start_pos + indicator as usize >= buffer.len() {
+                    return Return::Success(None);
+                }
+                let slice = &buffer[..(*start_pos + indicator as usize)];
+                return match T::convert(slice, encoding) {
+                    Ok(value) => Return::Success(Some(value)),
+                    Err(error) => Return::Failed(error),
+                };
             }
             ffi::SQL_SUCCESS_WITH_INFO => {
                 let initial_len = buffer.len();
@@ -86,25 +119,76 @@ impl<'p> Raii<'p, ffi::Stmt> {
                 // for buggy drivers here. They always can implement OdbcType trait and set any
                 // amount of null-terminators to do the workaround.
 
-                let null_offset = T::null_bytes_count();
+                let null_offset = T::null_bytes_count_for(encoding);
                 if indicator == ffi::SQL_NO_TOTAL {
                     buffer.resize(initial_len * 2, 0);
-                    self.get_partial_data(col_or_param_num, buffer, initial_len - null_offset)
+                    *start_pos = initial_len - null_offset;
+                } else if indicator >= initial_len as ffi::SQLLEN {
+                    // The value has been truncated
+                    buffer.resize(indicator as usize + null_offset, 0);
+                    *start_pos = initial_len - null_offset;
                 } else {
-                    // Check if string has been truncated.
-                    if indicator >= initial_len as ffi::SQLLEN {
-                        buffer.resize(indicator as usize + T::null_bytes_count(), 0);
-                        self.get_partial_data(col_or_param_num, buffer, initial_len - null_offset)
-                    } else {
-                        let slice = &buffer[..(start_pos + indicator as usize)];
-                        // No truncation. Warning may be due to some other issue.
-                        Return::SuccessWithInfo(Some(T::convert(slice)))
-                    }
+                    let slice = &buffer[..(*start_pos + indicator as usize)];
+                    // No truncation. Warning may be due to some other issue.
+                    return match T::convert(slice, encoding) {
+                        Ok(value) => Return::SuccessWithInfo(Some(value)),
+                        Err(error) => Return::Failed(error),
+                    };
                 }
             }
-            ffi::SQL_ERROR => Return::Error,
-            ffi::SQL_NO_DATA => panic!("SQLGetData has already returned the colmun data"),
-            r => panic!("unexpected return value from SQLGetData: {:?}", r),
+            ffi::SQL_ERROR => return Return::Error,
+            ffi::SQL_STILL_EXECUTING => return Return::StillExecuting,
+            r => return Return::unexpected("SQLGetData", r),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    /// Replays `steps` of data written, return code and indicator as `SQLGetData` would
+    fn mock_get_data<'s>(
+        steps: &'s [(&'s [u8], ffi::SQLRETURN, ffi::SQLLEN)],
+        calls: &'s mut usize,
+    ) -> impl FnMut(&mut [u8]) -> (ffi::SQLRETURN, ffi::SQLLEN) + 's {
+        move |chunk| {
+            let (data, result, indicator) = steps[*calls];
+            *calls += 1;
+            chunk[..data.len()].copy_from_slice(data);
+            (result, indicator)
+        }
+    }
+
+    #[test]
+    fn resumes_after_still_executing() {
+        let steps: &[(&[u8], ffi::SQLRETURN, ffi::SQLLEN)] = &[
+            (b"abc\0", ffi::SQL_SUCCESS_WITH_INFO, 8),
+            (b"", ffi::SQL_STILL_EXECUTING, 0),
+            (b"defgh\0", ffi::SQL_SUCCESS, 5),
+        ];
+        let encoding = TextEncoding::default();
+        let mut buffer = vec![0; 4];
+        let mut start_pos = 0;
+        let mut calls = 0;
+        let polled: Return<Option<String>> = read_chunks(
+            &mut buffer,
+            &mut start_pos,
+            encoding,
+            mock_get_data(steps, &mut calls),
+        );
+        assert!(matches!(polled, Return::StillExecuting));
+        assert_eq!(start_pos, 3);
+        let value: Return<Option<String>> = read_chunks(
+            &mut buffer,
+            &mut start_pos,
+            encoding,
+            mock_get_data(&steps[calls..], &mut 0),
+        );
+        match value {
+            Return::Success(Some(value)) => assert_eq!(value, "abcdefgh"),
+            _ => panic!("unexpected result"),
         }
     }
 }
@@ -0,0 +1,427 @@
+//! Native asynchronous execution. With `SQL_ATTR_ASYNC_ENABLE` set, the CLI returns
+//! `SQL_STILL_EXECUTING` instead of blocking and the function has to be called again, with the same
+//! arguments, until it completes. A single thread can drive many statements this way.
+//!
+//! The blocking functions of a statement report `SQL_STILL_EXECUTING` as an error, use the `poll_`
+//! functions or the futures in this module instead.
+use odbc_safe::AutocommitMode;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::OnceLock;
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+use {
+    super::super::ffi, super::super::Allocated, super::super::Cursor, super::super::Executed,
+    super::super::Handle, super::super::HasResult, super::super::NoResult, super::super::OdbcType,
+    super::super::Prepared, super::super::Raii, super::super::ResultSetState, super::super::Return,
+    super::super::Statement,
+};
+
+/// Outcome of polling a function which consumes the statement
+pub enum Polled<T, S> {
+    /// The function completed
+    Ready(T),
+    /// The function is still executing. Poll again using the returned statement.
+    StillExecuting(S),
+}
+
+impl<'a, 'b, S, R, AC: AutocommitMode> Statement<'a, 'b, S, R, AC> {
+    /// Enables or disables asynchronous execution of this statement (`SQL_ATTR_ASYNC_ENABLE`).
+    /// Can only be changed while no function is executing on the statement.
+    pub fn set_async_enable(&mut self, enabled: bool) -> crate::Result<()> {
+        let value = if enabled {
+            crate::SQL_ASYNC_ENABLE_ON
+        } else {
+            crate::SQL_ASYNC_ENABLE_OFF
+        };
+        self.raii
+            .set_stmt_attr_u32(crate::SQL_ATTR_ASYNC_ENABLE, value)
+            .into_result(&*self)
+    }
+
+    /// Requests cancellation of the function currently executing on this statement. A function
+    /// executing asynchronously reports the cancellation (`HY008`) the next time it is polled.
+    pub fn cancel(&self) -> crate::Result<()> {
+        self.raii.cancel().into_result(self)
+    }
+}
+
+impl<'a, 'b, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC> {
+    /// Like `exec_direct`, but returns the statement if the execution has been started
+    /// asynchronously and is not complete yet. Call again with the same statement text until
+    /// the result is `Polled::Ready`.
+    pub fn poll_exec_direct(
+        mut self,
+        statement_text: &str,
+    ) -> crate::Result<Polled<ResultSetState<'a, 'b, Executed, AC>, Self>> {
+        match self
+            .raii
+            .exec_direct(statement_text, self.encoding)
+            .into_poll(&self)?
+        {
+            Poll::Pending => Ok(Polled::StillExecuting(self)),
+            Poll::Ready(true) => {
+                let num_cols = self.raii.num_result_cols().into_result(&self)?;
+                if num_cols > 0 {
+                    Ok(Polled::Ready(ResultSetState::Data(self.transit())))
+                } else {
+                    Ok(Polled::Ready(ResultSetState::NoData(self.transit())))
+                }
+            }
+            Poll::Ready(false) => Ok(Polled::Ready(ResultSetState::NoData(self.transit()))),
+        }
+    }
+
+    /// Executes the statement asynchronously. Asynchronous execution has to be enabled on the
+    /// statement or its connection, otherwise the future completes on the first poll. While the
+    /// statement executes, the future polls the CLI with a growing delay, see `ExecDirect`.
+    pub fn exec_direct_async(self, statement_text: &str) -> ExecDirect<'a, 'b, AC> {
+        ExecDirect {
+            stmt: Some(self),
+            statement_text: statement_text.to_string(),
+            backoff: Backoff::default(),
+        }
+    }
+}
+
+impl<'a, 'b, AC: AutocommitMode> Statement<'a, 'b, Prepared, NoResult, AC> {
+    /// Like `execute`, but returns the statement if the execution has been started
+    /// asynchronously and is not complete yet. Call again until the result is `Polled::Ready`.
+    pub fn poll_execute(
+        mut self,
+    ) -> crate::Result<Polled<ResultSetState<'a, 'b, Prepared, AC>, Self>> {
+        match self.raii.execute().into_poll(&self)? {
+            Poll::Pending => Ok(Polled::StillExecuting(self)),
+            Poll::Ready(true) => {
+                let num_cols = self.raii.num_result_cols().into_result(&self)?;
+                if num_cols > 0 {
+                    Ok(Polled::Ready(ResultSetState::Data(self.transit())))
+                } else {
+                    Ok(Polled::Ready(ResultSetState::NoData(self.transit())))
+                }
+            }
+            Poll::Ready(false) => Ok(Polled::Ready(ResultSetState::NoData(self.transit()))),
+        }
+    }
+
+    /// Executes the prepared statement asynchronously. Asynchronous execution has to be enabled
+    /// on the statement or its connection, otherwise the future completes on the first poll.
+    /// While the statement executes, the future polls the CLI with a growing delay, see `Execute`.
+    pub fn execute_async(self) -> Execute<'a, 'b, AC> {
+        Execute {
+            stmt: Some(self),
+            backoff: Backoff::default(),
+        }
+    }
+}
+
+impl<'a, 'b, S, AC: AutocommitMode> Statement<'a, 'b, S, HasResult, AC> {
+    /// Like `fetch`, but returns `Poll::Pending` if the fetch has been started asynchronously and
+    /// is not complete yet.
+    pub fn poll_fetch<'s>(
+        &'s mut self,
+    ) -> crate::Result<Poll<Option<Cursor<'s, 'a, 'b, S, AC>>>> {
+        match self.raii.fetch().into_poll(self)? {
+            Poll::Pending => Ok(Poll::Pending),
+            Poll::Ready(true) => Ok(Poll::Ready(Some(Cursor {
+                stmt: self,
+                buffer: vec![0; 512],
+                pending: None,
+            }))),
+            Poll::Ready(false) => Ok(Poll::Ready(None)),
+        }
+    }
+}
+
+impl<'s, 'a: 's, 'b: 's, S: 's, AC: AutocommitMode> Cursor<'s, 'a, 'b, S, AC> {
+    /// Like `get_data`, but returns `Poll::Pending` if the call has been started asynchronously
+    /// and is not complete yet. Poll again for the same column until the value is ready, the data
+    /// read so far is kept by the cursor in between.
+    pub fn poll_get_data<'d, T>(
+        &'d mut self,
+        col_or_param_num: u16,
+    ) -> crate::Result<Poll<Option<T>>>
+    where
+        T: OdbcType<'d>,
+    {
+        let encoding = self.stmt.encoding;
+        let mut start_pos = match self.pending.take() {
+            Some((column, start_pos)) if column == col_or_param_num => start_pos,
+            _ => 0,
+        };
+        let ret = self.stmt.raii.get_data_from(
+            col_or_param_num,
+            &mut self.buffer,
+            &mut start_pos,
+            encoding,
+        );
+        if let Return::StillExecuting = ret {
+            self.pending = Some((col_or_param_num, start_pos));
+        }
+        ret.into_poll(self.stmt)
+    }
+}
+
+/// Future returned by `Statement::exec_direct_async`
+///
+/// The CLI does not signal when an asynchronous function completes. Whenever the statement is
+/// still executing, the future has its task woken again after a delay, which starts at 1 ms and
+/// doubles with every poll up to 50 ms, see `with_backoff`. The delays are kept by a timer thread
+/// shared by all futures of this module, so no particular runtime is needed.
+pub struct ExecDirect<'a, 'b, AC: AutocommitMode> {
+    stmt: Option<Statement<'a, 'b, Allocated, NoResult, AC>>,
+    statement_text: String,
+    backoff: Backoff,
+}
+
+impl<'a, 'b, AC: AutocommitMode> ExecDirect<'a, 'b, AC> {
+    /// Sets the delay before the first poll after `SQL_STILL_EXECUTING` and the maximum delay it
+    /// doubles up to. A zero `initial` delay wakes the task right away, which keeps its executor
+    /// thread busy until the statement completes.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = Backoff::new(initial, max);
+        self
+    }
+}
+
+// The statement is never pinned, it is moved in and out of the future between polls
+impl<'a, 'b, AC: AutocommitMode> Unpin for ExecDirect<'a, 'b, AC> {}
+
+impl<'a, 'b, AC: AutocommitMode> Future for ExecDirect<'a, 'b, AC> {
+    type Output = crate::Result<ResultSetState<'a, 'b, Executed, AC>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let stmt = this
+            .stmt
+            .take()
+            .expect("ExecDirect polled after completion");
+        match stmt.poll_exec_direct(&this.statement_text) {
+            Ok(Polled::Ready(state)) => Poll::Ready(Ok(state)),
+            Ok(Polled::StillExecuting(stmt)) => {
+                this.stmt = Some(stmt);
+                this.backoff.wake(cx.waker());
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+/// Future returned by `Statement::execute_async`
+///
+/// Polls `SQLExecute` with a growing delay while the statement executes, like `ExecDirect`.
+pub struct Execute<'a, 'b, AC: AutocommitMode> {
+    stmt: Option<Statement<'a, 'b, Prepared, NoResult, AC>>,
+    backoff: Backoff,
+}
+
+impl<'a, 'b, AC: AutocommitMode> Execute<'a, 'b, AC> {
+    /// Sets the delays between polls, see `ExecDirect::with_backoff`
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = Backoff::new(initial, max);
+        self
+    }
+}
+
+impl<'a, 'b, AC: AutocommitMode> Unpin for Execute<'a, 'b, AC> {}
+
+impl<'a, 'b, AC: AutocommitMode> Future for Execute<'a, 'b, AC> {
+    type Output = crate::Result<ResultSetState<'a, 'b, Prepared, AC>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let stmt = this.stmt.take().expect("Execute polled after completion");
+        match stmt.poll_execute() {
+            Ok(Polled::Ready(state)) => Poll::Ready(Ok(state)),
+            Ok(Polled::StillExecuting(stmt)) => {
+                this.stmt = Some(stmt);
+                this.backoff.wake(cx.waker());
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+/// Delay before a future polls a statement which is still executing again
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Backoff {
+    next: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    fn new(initial: Duration, max: Duration) -> Backoff {
+        Backoff { next: initial, max }
+    }
+
+    /// Returns the current delay and doubles it for the next poll
+    fn advance(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (delay * 2).min(self.max);
+        delay
+    }
+
+    /// Has `waker` woken after the current delay
+    fn wake(&mut self, waker: &Waker) {
+        let delay = self.advance();
+        if delay == Duration::from_secs(0) {
+            waker.wake_by_ref();
+        } else {
+            wake_after(waker.clone(), delay);
+        }
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Backoff {
+        Backoff::new(Duration::from_millis(1), Duration::from_millis(50))
+    }
+}
+
+/// Task to wake once `at` has passed
+struct Wakeup {
+    at: Instant,
+    waker: Waker,
+}
+
+impl PartialEq for Wakeup {
+    fn eq(&self, other: &Wakeup) -> bool {
+        self.at == other.at
+    }
+}
+
+impl Eq for Wakeup {}
+
+impl PartialOrd for Wakeup {
+    fn partial_cmp(&self, other: &Wakeup) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Wakeup {
+    fn cmp(&self, other: &Wakeup) -> Ordering {
+        self.at.cmp(&other.at)
+    }
+}
+
+/// Wakes `waker` once `delay` has passed, on a timer thread started on first use. Wakes it right
+/// away if the thread can not be started.
+fn wake_after(waker: Waker, delay: Duration) {
+    static TIMER: OnceLock<Option<mpsc::Sender<Wakeup>>> = OnceLock::new();
+    let timer = TIMER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        match thread::Builder::new()
+            .name("ibm_db-poll-timer".to_string())
+            .spawn(move || run_timer(receiver))
+        {
+            Ok(_) => Some(sender),
+            Err(e) => {
+                error!("Error starting the timer thread of asynchronous statements: {}", e);
+                None
+            }
+        }
+    });
+    let wakeup = Wakeup {
+        at: Instant::now() + delay,
+        waker,
+    };
+    match timer {
+        Some(sender) => {
+            if let Err(mpsc::SendError(wakeup)) = sender.send(wakeup) {
+                wakeup.waker.wake();
+            }
+        }
+        None => wakeup.waker.wake(),
+    }
+}
+
+/// Runs the timer thread, waking every received task once its time has come
+fn run_timer(receiver: mpsc::Receiver<Wakeup>) {
+    let mut pending = BinaryHeap::new();
+    loop {
+        let received = match pending.peek() {
+            Some(Reverse(Wakeup { at, .. })) => {
+                receiver.recv_timeout(at.saturating_duration_since(Instant::now()))
+            }
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(wakeup) => pending.push(Reverse(wakeup)),
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        let now = Instant::now();
+        while let Some(Reverse(wakeup)) = pending.peek() {
+            if wakeup.at > now {
+                break;
+            }
+            if let Some(Reverse(wakeup)) = pending.pop() {
+                wakeup.waker.wake();
+            }
+        }
+    }
+}
+
+impl<'p> Raii<'p, ffi::Stmt> {
+    fn set_stmt_attr_u32(&mut self, attribute: u32, value: u32) -> Return<()> {
+        let ret = unsafe {
+            crate::SQLSetStmtAttr(
+                ffi::cli_handle(self.handle()),
+                attribute as crate::SQLINTEGER,
+                value as usize as crate::SQLPOINTER,
+                0,
+            )
+        };
+        Return::from_cli("SQLSetStmtAttr", ret, ())
+    }
+
+    fn cancel(&self) -> Return<()> {
+        match unsafe { ffi::SQLCancel(self.handle()) } {
+            ffi::SQL_SUCCESS => Return::Success(()),
+            ffi::SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(()),
+            ffi::SQL_ERROR => Return::Error,
+            r => Return::unexpected("SQLCancel", r),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, AtomicOrdering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let mut backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(5));
+        assert_eq!(backoff.advance(), Duration::from_millis(1));
+        assert_eq!(backoff.advance(), Duration::from_millis(2));
+        assert_eq!(backoff.advance(), Duration::from_millis(4));
+        assert_eq!(backoff.advance(), Duration::from_millis(5));
+        assert_eq!(backoff.advance(), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn wakes_after_delay() {
+        let flag = Arc::new(Flag(AtomicBool::new(false)));
+        wake_after(Waker::from(flag.clone()), Duration::from_millis(20));
+        assert!(!flag.0.load(AtomicOrdering::SeqCst));
+        thread::sleep(Duration::from_millis(500));
+        assert!(flag.0.load(AtomicOrdering::SeqCst));
+    }
+}
@@ -1,10 +1,11 @@
 #![allow(bare_trait_objects)]
+use super::super::encoding::to_wide;
 use odbc_safe::AutocommitMode;
-use std::error::Error;
 use {
     super::super::ffi, super::super::Allocated, super::super::ColumnDescriptor,
     super::super::Handle, super::super::NoResult, super::super::Prepared, super::super::Raii,
     super::super::ResultSetState, super::super::Return, super::super::Statement,
+    super::super::TextEncoding,
 };
 
 impl<'a, 'b, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC> {
@@ -46,9 +47,11 @@ impl<'a, 'b, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC> {
     pub fn prepare(
         mut self,
         sql_text: &str,
-    ) -> Result<Statement<'a, 'b, Prepared, NoResult, AC>, Box<dyn Error>> {
-        self.raii.prepare(sql_text).into_result(&self)?;
-        Ok(Statement::with_raii(self.raii))
+    ) -> crate::Result<Statement<'a, 'b, Prepared, NoResult, AC>> {
+        self.raii
+            .prepare(sql_text, self.encoding)
+            .into_result(&self)?;
+        Ok(self.transit())
     }
 
     /// Prepares a statement for execution. Executing a prepared statement is faster than directly
@@ -73,9 +76,9 @@ impl<'a, 'b, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC> {
     pub fn prepare_bytes(
         mut self,
         bytes: &[u8],
-    ) -> Result<Statement<'a, 'b, Prepared, NoResult, AC>, Box<dyn Error>> {
+    ) -> crate::Result<Statement<'a, 'b, Prepared, NoResult, AC>> {
         self.raii.prepare_byte(bytes).into_result(&self)?;
-        Ok(Statement::with_raii(self.raii))
+        Ok(self.transit())
     }
 }
 
@@ -84,47 +87,52 @@ impl<'a, 'b, AC: AutocommitMode> Statement<'a, 'b, Prepared, NoResult, AC> {
     ///
     /// Can be called successfully only when the statement is in the prepared, executed, or
     /// positioned state. If the statement does not return columns the result will be 0.
-    pub fn num_result_cols(&self) -> Result<i16, Box<dyn Error>> {
+    pub fn num_result_cols(&self) -> crate::Result<i16> {
         self.raii.num_result_cols().into_result(self)?;
         Ok(0)
     }
 
     /// Returns description struct for result set column with a given index. Note: indexing is starting from 1.
     pub fn describe_col(&self, idx: u16) -> crate::Result<ColumnDescriptor> {
-        self.raii.describe_col(idx).into_result(self)
+        self.raii.describe_col(idx, self.encoding).into_result(self)
     }
 
     /// Executes a prepared statement.
-    pub fn execute(mut self) -> Result<ResultSetState<'a, 'b, Prepared, AC>, Box<dyn Error>> {
+    pub fn execute(mut self) -> crate::Result<ResultSetState<'a, 'b, Prepared, AC>> {
         if self.raii.execute().into_result(&self)? {
             let num_cols = self.raii.num_result_cols().into_result(&self)?;
             if num_cols > 0 {
-                Ok(ResultSetState::Data(Statement::with_raii(self.raii)))
+                Ok(ResultSetState::Data(self.transit()))
             } else {
-                Ok(ResultSetState::NoData(Statement::with_raii(self.raii)))
+                Ok(ResultSetState::NoData(self.transit()))
             }
         } else {
-            Ok(ResultSetState::NoData(Statement::with_raii(self.raii)))
+            Ok(ResultSetState::NoData(self.transit()))
         }
     }
 }
 
 impl<'p> Raii<'p, ffi::Stmt> {
-    fn prepare(&mut self, sql_text: &str) -> Return<()> {
-        let bytes = unsafe { crate::environment::DB_ENCODING }
-            .encode(sql_text)
-            .0;
-        match unsafe {
-            ffi::SQLPrepare(
-                self.handle(),
-                bytes.as_ptr(),
-                bytes.len() as ffi::SQLINTEGER,
-            )
-        } {
+    fn prepare(&mut self, sql_text: &str, encoding: TextEncoding) -> Return<()> {
+        let ret = if encoding.is_wide() {
+            let text = to_wide(sql_text);
+            unsafe { ffi::SQLPrepareW(self.handle(), text.as_ptr(), text.len() as ffi::SQLINTEGER) }
+        } else {
+            let bytes = encoding.db().encode(sql_text).0;
+            unsafe {
+                ffi::SQLPrepare(
+                    self.handle(),
+                    bytes.as_ptr(),
+                    bytes.len() as ffi::SQLINTEGER,
+                )
+            }
+        };
+        match ret {
             ffi::SQL_SUCCESS => Return::Success(()),
             ffi::SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(()),
             ffi::SQL_ERROR => Return::Error,
-            r => panic!("SQLPrepare returned unexpected result: {:?}", r),
+            ffi::SQL_STILL_EXECUTING => Return::StillExecuting,
+            r => Return::unexpected("SQLPrepare", r),
         }
     }
 
@@ -139,17 +147,19 @@ impl<'p> Raii<'p, ffi::Stmt> {
             ffi::SQL_SUCCESS => Return::Success(()),
             ffi::SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(()),
             ffi::SQL_ERROR => Return::Error,
-            r => panic!("SQLPrepare returned unexpected result: {:?}", r),
+            ffi::SQL_STILL_EXECUTING => Return::StillExecuting,
+            r => Return::unexpected("SQLPrepare", r),
         }
     }
 
-    fn execute(&mut self) -> Return<bool> {
+    pub(crate) fn execute(&mut self) -> Return<bool> {
         match unsafe { ffi::SQLExecute(self.handle()) } {
             ffi::SQL_SUCCESS => Return::Success(true),
             ffi::SQL_SUCCESS_WITH_INFO => Return::SuccessWithInfo(true),
             ffi::SQL_ERROR => Return::Error,
             ffi::SQL_NO_DATA => Return::Success(false),
-            r => panic!("SQLExecute returned unexpected result: {:?}", r),
+            ffi::SQL_STILL_EXECUTING => Return::StillExecuting,
+            r => Return::unexpected("SQLExecute", r),
         }
     }
 }
@@ -0,0 +1,331 @@
+use super::super::encoding::to_wide;
+use odbc_safe::AutocommitMode;
+use std::borrow::Cow;
+use std::ptr::null;
+use {
+    super::super::ffi, super::super::Allocated, super::super::Connection, super::super::Executed,
+    super::super::HasResult, super::super::Handle, super::super::NoResult, super::super::Raii,
+    super::super::Return, super::super::Statement, super::super::TextEncoding,
+};
+
+/// A privilege granted on a table, as reported by `SQLTablePrivileges`
+///
+/// Can be obtained via `Statement::table_privileges` or `Connection::table_privileges`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TablePrivilege {
+    /// Catalog of the table, `None` if not applicable to the data source
+    pub table_catalog: Option<String>,
+    /// Schema of the table
+    pub table_schema: Option<String>,
+    /// Name of the table
+    pub table_name: String,
+    /// Authorization ID of the user who granted the privilege, `None` if not known
+    pub grantor: Option<String>,
+    /// Authorization ID of the user or group to whom the privilege was granted
+    pub grantee: String,
+    /// Granted privilege, e.g. `SELECT`, `INSERT`, `UPDATE`, `DELETE`, `REFERENCES`, `ALTER`,
+    /// `INDEX` or `CONTROL`
+    pub privilege: String,
+    /// Whether the grantee may grant the privilege to others, `None` if unknown
+    pub is_grantable: Option<bool>,
+}
+
+/// A privilege granted on a column, as reported by `SQLColumnPrivileges`
+///
+/// Can be obtained via `Statement::column_privileges` or `Connection::column_privileges`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ColumnPrivilege {
+    /// Catalog of the table, `None` if not applicable to the data source
+    pub table_catalog: Option<String>,
+    /// Schema of the table
+    pub table_schema: Option<String>,
+    /// Name of the table
+    pub table_name: String,
+    /// Name of the column
+    pub column_name: String,
+    /// Authorization ID of the user who granted the privilege, `None` if not known
+    pub grantor: Option<String>,
+    /// Authorization ID of the user or group to whom the privilege was granted
+    pub grantee: String,
+    /// Granted privilege, e.g. `SELECT`, `INSERT`, `UPDATE` or `REFERENCES`
+    pub privilege: String,
+    /// Whether the grantee may grant the privilege to others, `None` if unknown
+    pub is_grantable: Option<bool>,
+}
+
+impl<'a, 'b, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC> {
+    /// Returns a result set with the privileges granted on the tables matching the given
+    /// patterns. `None` matches every catalog, schema or table respectively.
+    ///
+    /// The columns of the result set are `TABLE_CAT`, `TABLE_SCHEM`, `TABLE_NAME`, `GRANTOR`,
+    /// `GRANTEE`, `PRIVILEGE` and `IS_GRANTABLE`.
+    pub fn table_privileges_opt_str(
+        mut self,
+        catalog_name: Option<&str>,
+        schema_name: Option<&str>,
+        table_name: Option<&str>,
+    ) -> crate::Result<Statement<'a, 'b, Executed, HasResult, AC>> {
+        self.raii
+            .table_privileges(catalog_name, schema_name, table_name, self.encoding)
+            .into_result(&self)?;
+        Ok(self.transit())
+    }
+
+    /// Lists the privileges granted on the tables matching the given patterns. `None` matches
+    /// every catalog, schema or table respectively.
+    pub fn table_privileges(
+        self,
+        catalog_name: Option<&str>,
+        schema_name: Option<&str>,
+        table_name: Option<&str>,
+    ) -> crate::Result<Vec<TablePrivilege>> {
+        let mut stmt = self.table_privileges_opt_str(catalog_name, schema_name, table_name)?;
+        let mut privileges = Vec::new();
+        while let Some(mut cursor) = stmt.fetch()? {
+            privileges.push(TablePrivilege {
+                table_catalog: cursor.get_data::<String>(1)?,
+                table_schema: cursor.get_data::<String>(2)?,
+                table_name: cursor.get_data::<String>(3)?.unwrap_or_default(),
+                grantor: cursor.get_data::<String>(4)?,
+                grantee: cursor.get_data::<String>(5)?.unwrap_or_default(),
+                privilege: cursor.get_data::<String>(6)?.unwrap_or_default(),
+                is_grantable: parse_is_grantable(cursor.get_data::<String>(7)?.as_deref()),
+            });
+        }
+        Ok(privileges)
+    }
+
+    /// Returns a result set with the privileges granted on the columns of a single table.
+    /// `column_name` is a pattern, `None` matches every column. The table has to be named
+    /// explicitly, DB2 does not accept a search pattern or `NULL` for it.
+    ///
+    /// The columns of the result set are `TABLE_CAT`, `TABLE_SCHEM`, `TABLE_NAME`,
+    /// `COLUMN_NAME`, `GRANTOR`, `GRANTEE`, `PRIVILEGE` and `IS_GRANTABLE`.
+    pub fn column_privileges_opt_str(
+        mut self,
+        catalog_name: Option<&str>,
+        schema_name: Option<&str>,
+        table_name: &str,
+        column_name: Option<&str>,
+    ) -> crate::Result<Statement<'a, 'b, Executed, HasResult, AC>> {
+        let encoding = self.encoding;
+        self.raii
+            .column_privileges(catalog_name, schema_name, table_name, column_name, encoding)
+            .into_result(&self)?;
+        Ok(self.transit())
+    }
+
+    /// Lists the privileges granted on the columns of a single table. `column_name` is a
+    /// pattern, `None` matches every column.
+    pub fn column_privileges(
+        self,
+        catalog_name: Option<&str>,
+        schema_name: Option<&str>,
+        table_name: &str,
+        column_name: Option<&str>,
+    ) -> crate::Result<Vec<ColumnPrivilege>> {
+        let mut stmt =
+            self.column_privileges_opt_str(catalog_name, schema_name, table_name, column_name)?;
+        let mut privileges = Vec::new();
+        while let Some(mut cursor) = stmt.fetch()? {
+            privileges.push(ColumnPrivilege {
+                table_catalog: cursor.get_data::<String>(1)?,
+                table_schema: cursor.get_data::<String>(2)?,
+                table_name: cursor.get_data::<String>(3)?.unwrap_or_default(),
+                column_name: cursor.get_data::<String>(4)?.unwrap_or_default(),
+                grantor: cursor.get_data::<String>(5)?,
+                grantee: cursor.get_data::<String>(6)?.unwrap_or_default(),
+                privilege: cursor.get_data::<String>(7)?.unwrap_or_default(),
+                is_grantable: parse_is_grantable(cursor.get_data::<String>(8)?.as_deref()),
+            });
+        }
+        Ok(privileges)
+    }
+}
+
+impl<'env, AC: AutocommitMode> Connection<'env, AC> {
+    /// Lists the privileges granted on the tables matching the given patterns. See
+    /// `Statement::table_privileges`.
+    ///
+    /// Fails with `NotSupported` if the data source does not implement `SQLTablePrivileges`.
+    pub fn table_privileges(
+        &self,
+        catalog_name: Option<&str>,
+        schema_name: Option<&str>,
+        table_name: Option<&str>,
+    ) -> crate::Result<Vec<TablePrivilege>> {
+        self.require_function(crate::SQL_API_SQLTABLEPRIVILEGES, "SQLTablePrivileges")?;
+        Statement::with_parent(self)?.table_privileges(catalog_name, schema_name, table_name)
+    }
+
+    /// Lists the privileges granted on the columns of a single table. See
+    /// `Statement::column_privileges`.
+    ///
+    /// Fails with `NotSupported` if the data source does not implement `SQLColumnPrivileges`.
+    pub fn column_privileges(
+        &self,
+        catalog_name: Option<&str>,
+        schema_name: Option<&str>,
+        table_name: &str,
+        column_name: Option<&str>,
+    ) -> crate::Result<Vec<ColumnPrivilege>> {
+        self.require_function(crate::SQL_API_SQLCOLUMNPRIVILEGES, "SQLColumnPrivileges")?;
+        Statement::with_parent(self)?.column_privileges(
+            catalog_name,
+            schema_name,
+            table_name,
+            column_name,
+        )
+    }
+}
+
+/// `IS_GRANTABLE` is `YES`, `NO` or `NULL` if unknown
+fn parse_is_grantable(value: Option<&str>) -> Option<bool> {
+    match value.map(str::trim) {
+        Some("YES") => Some(true),
+        Some("NO") => Some(false),
+        _ => None,
+    }
+}
+
+/// Encodes an optional catalog function argument. `None` is passed as a null pointer.
+fn encode_name<'n>(name: Option<&'n str>, encoding: TextEncoding) -> Option<Cow<'n, [u8]>> {
+    name.map(|name| encoding.db().encode(name).0)
+}
+
+fn name_ptr(name: &Option<Cow<[u8]>>) -> (*const ffi::SQLCHAR, ffi::SQLSMALLINT) {
+    match name {
+        Some(bytes) => (bytes.as_ptr(), bytes.len() as ffi::SQLSMALLINT),
+        None => (null(), 0),
+    }
+}
+
+/// Like `encode_name`, for the wide functions
+fn encode_wide_name(name: Option<&str>) -> Option<Vec<u16>> {
+    name.map(to_wide)
+}
+
+fn wide_name_ptr(name: &Option<Vec<u16>>) -> (*const ffi::SQLWCHAR, ffi::SQLSMALLINT) {
+    match name {
+        Some(units) => (units.as_ptr(), units.len() as ffi::SQLSMALLINT),
+        None => (null(), 0),
+    }
+}
+
+impl<'p> Raii<'p, ffi::Stmt> {
+    fn table_privileges(
+        &mut self,
+        catalog_name: Option<&str>,
+        schema_name: Option<&str>,
+        table_name: Option<&str>,
+        encoding: TextEncoding,
+    ) -> Return<()> {
+        let ret = if encoding.is_wide() {
+            let catalog = encode_wide_name(catalog_name);
+            let schema = encode_wide_name(schema_name);
+            let table = encode_wide_name(table_name);
+            let (catalog_ptr, catalog_len) = wide_name_ptr(&catalog);
+            let (schema_ptr, schema_len) = wide_name_ptr(&schema);
+            let (table_ptr, table_len) = wide_name_ptr(&table);
+            unsafe {
+                crate::SQLTablePrivilegesW(
+                    ffi::cli_handle(self.handle()),
+                    catalog_ptr as *mut crate::SQLWCHAR,
+                    catalog_len,
+                    schema_ptr as *mut crate::SQLWCHAR,
+                    schema_len,
+                    table_ptr as *mut crate::SQLWCHAR,
+                    table_len,
+                )
+            }
+        } else {
+            let catalog = encode_name(catalog_name, encoding);
+            let schema = encode_name(schema_name, encoding);
+            let table = encode_name(table_name, encoding);
+            let (catalog_ptr, catalog_len) = name_ptr(&catalog);
+            let (schema_ptr, schema_len) = name_ptr(&schema);
+            let (table_ptr, table_len) = name_ptr(&table);
+            unsafe {
+                crate::SQLTablePrivileges(
+                    ffi::cli_handle(self.handle()),
+                    catalog_ptr as *mut crate::SQLCHAR,
+                    catalog_len,
+                    schema_ptr as *mut crate::SQLCHAR,
+                    schema_len,
+                    table_ptr as *mut crate::SQLCHAR,
+                    table_len,
+                )
+            }
+        };
+        Return::from_cli("SQLTablePrivileges", ret, ())
+    }
+
+    fn column_privileges(
+        &mut self,
+        catalog_name: Option<&str>,
+        schema_name: Option<&str>,
+        table_name: &str,
+        column_name: Option<&str>,
+        encoding: TextEncoding,
+    ) -> Return<()> {
+        let ret = if encoding.is_wide() {
+            let catalog = encode_wide_name(catalog_name);
+            let schema = encode_wide_name(schema_name);
+            let table = encode_wide_name(Some(table_name));
+            let column = encode_wide_name(column_name);
+            let (catalog_ptr, catalog_len) = wide_name_ptr(&catalog);
+            let (schema_ptr, schema_len) = wide_name_ptr(&schema);
+            let (table_ptr, table_len) = wide_name_ptr(&table);
+            let (column_ptr, column_len) = wide_name_ptr(&column);
+            unsafe {
+                ffi::SQLColumnPrivilegesW(
+                    self.handle(),
+                    catalog_ptr,
+                    catalog_len,
+                    schema_ptr,
+                    schema_len,
+                    table_ptr,
+                    table_len,
+                    column_ptr,
+                    column_len,
+                ) as crate::SQLRETURN
+            }
+        } else {
+            let catalog = encode_name(catalog_name, encoding);
+            let schema = encode_name(schema_name, encoding);
+            let table = encode_name(Some(table_name), encoding);
+            let column = encode_name(column_name, encoding);
+            let (catalog_ptr, catalog_len) = name_ptr(&catalog);
+            let (schema_ptr, schema_len) = name_ptr(&schema);
+            let (table_ptr, table_len) = name_ptr(&table);
+            let (column_ptr, column_len) = name_ptr(&column);
+            unsafe {
+                crate::SQLColumnPrivileges(
+                    ffi::cli_handle(self.handle()),
+                    catalog_ptr as *mut crate::SQLCHAR,
+                    catalog_len,
+                    schema_ptr as *mut crate::SQLCHAR,
+                    schema_len,
+                    table_ptr as *mut crate::SQLCHAR,
+                    table_len,
+                    column_ptr as *mut crate::SQLCHAR,
+                    column_len,
+                )
+            }
+        };
+        Return::from_cli("SQLColumnPrivileges", ret, ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn is_grantable() {
+        assert_eq!(parse_is_grantable(Some("YES")), Some(true));
+        assert_eq!(parse_is_grantable(Some("NO ")), Some(false));
+        assert_eq!(parse_is_grantable(None), None);
+    }
+}
@@ -0,0 +1,153 @@
+use odbc_safe::AutocommitMode;
+use {
+    super::super::ffi, super::super::Allocated, super::super::Connection, super::super::Executed,
+    super::super::HasResult, super::super::Handle, super::super::NoResult, super::super::Raii,
+    super::super::Return, super::super::Statement,
+};
+
+/// Pass as `data_type` to `Statement::type_info` to list every type supported by the data source
+pub const ALL_TYPES: i16 = crate::SQL_ALL_TYPES as i16;
+
+/// How a column of a data type may be used in a `WHERE` clause
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Searchable {
+    /// The type can not be used in a `WHERE` clause
+    Unsearchable,
+    /// The type can only be used with `LIKE`
+    LikeOnly,
+    /// The type can be used with all comparison operators except `LIKE`
+    AllExceptLike,
+    /// The type can be used with any comparison operator
+    Searchable,
+}
+
+impl Searchable {
+    fn from_raw(value: i16) -> Option<Searchable> {
+        match value as u32 {
+            crate::SQL_UNSEARCHABLE => Some(Searchable::Unsearchable),
+            crate::SQL_LIKE_ONLY => Some(Searchable::LikeOnly),
+            crate::SQL_ALL_EXCEPT_LIKE => Some(Searchable::AllExceptLike),
+            crate::SQL_SEARCHABLE => Some(Searchable::Searchable),
+            _ => None,
+        }
+    }
+}
+
+/// Description of a data type supported by the data source, as reported by `SQLGetTypeInfo`
+///
+/// Can be obtained via `Statement::type_info` or `Connection::type_info`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TypeInfo {
+    /// Data source dependent name of the type, e.g. `DECFLOAT`, `XML` or `GRAPHIC`
+    pub type_name: String,
+    /// SQL data type code. DB2 specific types use the codes defined by DB2 CLI, e.g. `SQL_XML`
+    /// or `SQL_DECFLOAT`, which is why this is not an `ffi::SqlDataType`.
+    pub data_type: i16,
+    /// Maximum column size (precision for numeric types), `None` if not applicable
+    pub column_size: Option<i32>,
+    /// Characters used to prefix a literal of this type, e.g. `'` or `X'`
+    pub literal_prefix: Option<String>,
+    /// Characters used to terminate a literal of this type
+    pub literal_suffix: Option<String>,
+    /// Comma separated keywords of the parameters used to create a column of this type, e.g.
+    /// `precision,scale`
+    pub create_params: Option<String>,
+    /// Whether the type accepts `NULL` values, `None` if unknown
+    pub nullable: Option<bool>,
+    /// Whether a character type is case sensitive in collations and comparisons
+    pub case_sensitive: bool,
+    /// How the type may be used in a `WHERE` clause
+    pub searchable: Option<Searchable>,
+    /// Whether the type is unsigned, `None` if the attribute does not apply to the type
+    pub unsigned_attribute: Option<bool>,
+    /// Whether the type has a fixed precision and scale, like a money type
+    pub fixed_prec_scale: bool,
+    /// Whether the type is auto incrementing, `None` if the attribute does not apply to the type
+    pub auto_unique_value: Option<bool>,
+    /// Localized name of the type
+    pub local_type_name: Option<String>,
+    /// Minimum scale of the type, `None` if scale is not applicable
+    pub minimum_scale: Option<i16>,
+    /// Maximum scale of the type, `None` if scale is not applicable
+    pub maximum_scale: Option<i16>,
+    /// Value of the `SQL_DESC_TYPE` descriptor field, equal to `data_type` except for date time
+    /// and interval types
+    pub sql_data_type: i16,
+    /// Subcode of date time and interval types
+    pub sql_datetime_sub: Option<i16>,
+    /// Radix used to interpret `column_size`, either 2 or 10
+    pub num_prec_radix: Option<i32>,
+    /// Leading interval precision of interval types
+    pub interval_precision: Option<i16>,
+}
+
+impl<'a, 'b, AC: AutocommitMode> Statement<'a, 'b, Allocated, NoResult, AC> {
+    /// Returns a result set describing the data types supported by the data source. Pass
+    /// `ALL_TYPES` to describe every type, or an SQL data type code to describe only that type.
+    ///
+    /// The result set has the columns defined for `SQLGetTypeInfo`, starting with `TYPE_NAME`,
+    /// `DATA_TYPE` and `COLUMN_SIZE`.
+    pub fn get_type_info(
+        mut self,
+        data_type: i16,
+    ) -> crate::Result<Statement<'a, 'b, Executed, HasResult, AC>> {
+        self.raii.get_type_info(data_type).into_result(&self)?;
+        Ok(self.transit())
+    }
+
+    /// Lists the data types supported by the data source. Pass `ALL_TYPES` to describe every type,
+    /// or an SQL data type code to describe only that type.
+    pub fn type_info(self, data_type: i16) -> crate::Result<Vec<TypeInfo>> {
+        let mut stmt = self.get_type_info(data_type)?;
+        let mut types = Vec::new();
+        while let Some(mut cursor) = stmt.fetch()? {
+            let data_type = cursor.get_data::<i16>(2)?.unwrap_or_default();
+            types.push(TypeInfo {
+                type_name: cursor.get_data::<String>(1)?.unwrap_or_default(),
+                data_type,
+                column_size: cursor.get_data::<i32>(3)?,
+                literal_prefix: cursor.get_data::<String>(4)?,
+                literal_suffix: cursor.get_data::<String>(5)?,
+                create_params: cursor.get_data::<String>(6)?,
+                nullable: match cursor.get_data::<i16>(7)? {
+                    Some(0) => Some(false),
+                    Some(1) => Some(true),
+                    _ => None,
+                },
+                case_sensitive: cursor.get_data::<i16>(8)? == Some(1),
+                searchable: cursor
+                    .get_data::<i16>(9)?
+                    .and_then(Searchable::from_raw),
+                unsigned_attribute: cursor.get_data::<i16>(10)?.map(|v| v == 1),
+                fixed_prec_scale: cursor.get_data::<i16>(11)? == Some(1),
+                auto_unique_value: cursor.get_data::<i16>(12)?.map(|v| v == 1),
+                local_type_name: cursor.get_data::<String>(13)?,
+                minimum_scale: cursor.get_data::<i16>(14)?,
+                maximum_scale: cursor.get_data::<i16>(15)?,
+                sql_data_type: cursor.get_data::<i16>(16)?.unwrap_or(data_type),
+                sql_datetime_sub: cursor.get_data::<i16>(17)?,
+                num_prec_radix: cursor.get_data::<i32>(18)?,
+                interval_precision: cursor.get_data::<i16>(19)?,
+            });
+        }
+        Ok(types)
+    }
+}
+
+impl<'env, AC: AutocommitMode> Connection<'env, AC> {
+    /// Lists every data type supported by the data source. Useful to adapt generated DDL to the
+    /// server version, e.g. to find out whether `BOOLEAN` or `DECFLOAT` are available.
+    ///
+    /// Fails with `NotSupported` if the data source does not implement `SQLGetTypeInfo`.
+    pub fn type_info(&self) -> crate::Result<Vec<TypeInfo>> {
+        self.require_function(crate::SQL_API_SQLGETTYPEINFO, "SQLGetTypeInfo")?;
+        Statement::with_parent(self)?.type_info(ALL_TYPES)
+    }
+}
+
+impl<'p> Raii<'p, ffi::Stmt> {
+    fn get_type_info(&mut self, data_type: i16) -> Return<()> {
+        let ret = unsafe { crate::SQLGetTypeInfo(ffi::cli_handle(self.handle()), data_type) };
+        Return::from_cli("SQLGetTypeInfo", ret, ())
+    }
+}
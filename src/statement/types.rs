@@ -1,7 +1,8 @@
-use super::super::ffi;
+use super::super::{ffi, Error, Result, TextEncoding};
 use std::borrow::Cow::{Borrowed, Owned};
+use std::any::type_name;
 use std::ffi::CString;
-use std::mem::{size_of, transmute};
+use std::mem::size_of;
 use std::slice::from_raw_parts;
 
 pub struct EncodedValue {
@@ -37,16 +38,27 @@ impl EncodedValue {
 pub unsafe trait OdbcType<'a>: Sized {
     fn sql_data_type() -> ffi::SqlDataType;
     fn c_data_type() -> ffi::SqlCDataType;
-    fn convert(_: &'a [u8]) -> Self;
+    /// C type the value is exchanged as with `encoding`, e.g. `SQL_C_WCHAR` instead of
+    /// `SQL_C_CHAR` for text if the wide functions are used
+    fn c_data_type_for(_: TextEncoding) -> ffi::SqlCDataType {
+        Self::c_data_type()
+    }
+    fn convert(_: &'a [u8], encoding: TextEncoding) -> Result<Self>;
     fn column_size(&self) -> ffi::SQLULEN;
     fn null_bytes_count() -> usize {
         0
     }
+    /// Size of the terminating null written by the CLI for `c_data_type_for(encoding)`
+    fn null_bytes_count_for(_: TextEncoding) -> usize {
+        Self::null_bytes_count()
+    }
     fn value_ptr(&self) -> ffi::SQLPOINTER;
     fn decimal_digits(&self) -> ffi::SQLSMALLINT {
         0
     }
-    fn encoded_value(&self) -> EncodedValue;
+    /// Bound instead of `value_ptr` and `column_size` if it has a value, e.g. text encoded with
+    /// the DB encoding of the statement
+    fn encoded_value(&self, encoding: TextEncoding) -> EncodedValue;
 }
 
 unsafe impl<'a> OdbcType<'a> for &'a [u8] {
@@ -57,8 +69,8 @@ unsafe impl<'a> OdbcType<'a> for &'a [u8] {
         ffi::SQL_C_BINARY
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        buffer
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
+        Ok(buffer)
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
@@ -69,7 +81,7 @@ unsafe impl<'a> OdbcType<'a> for &'a [u8] {
         self.as_ptr() as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -82,8 +94,8 @@ unsafe impl<'a> OdbcType<'a> for Vec<u8> {
         ffi::SQL_C_BINARY
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        buffer.to_vec()
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
+        Ok(buffer.to_vec())
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
@@ -94,7 +106,7 @@ unsafe impl<'a> OdbcType<'a> for Vec<u8> {
         self.as_ptr() as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -107,8 +119,8 @@ unsafe impl<'a> OdbcType<'a> for &'a [u16] {
         ffi::SQL_C_WCHAR
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        unsafe { from_raw_parts(buffer.as_ptr() as *const u16, buffer.len() / 2) }
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
+        Ok(unsafe { from_raw_parts(buffer.as_ptr() as *const u16, buffer.len() / 2) })
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
@@ -123,7 +135,7 @@ unsafe impl<'a> OdbcType<'a> for &'a [u16] {
         self.as_ptr() as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -136,9 +148,9 @@ unsafe impl<'a> OdbcType<'a> for Vec<u16> {
         ffi::SQL_C_WCHAR
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
         let buffer = unsafe { from_raw_parts(buffer.as_ptr() as *const u16, buffer.len() / 2) };
-        buffer.to_vec()
+        Ok(buffer.to_vec())
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
@@ -153,7 +165,7 @@ unsafe impl<'a> OdbcType<'a> for Vec<u16> {
         self.as_ptr() as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -166,8 +178,8 @@ unsafe impl<'a> OdbcType<'a> for CString {
         ffi::SQL_C_CHAR
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        CString::new(buffer).unwrap()
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
+        CString::new(buffer).map_err(|e| Error::Conversion(e.to_string()))
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
@@ -182,7 +194,7 @@ unsafe impl<'a> OdbcType<'a> for CString {
         1
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -194,39 +206,32 @@ unsafe impl<'a> OdbcType<'a> for String {
     fn c_data_type() -> ffi::SqlCDataType {
         ffi::SQL_C_CHAR
     }
+    fn c_data_type_for(encoding: TextEncoding) -> ffi::SqlCDataType {
+        text_c_data_type(encoding)
+    }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        unsafe { super::super::environment::DB_ENCODING }
-            .decode(buffer)
-            .0
-            .to_string()
+    fn convert(buffer: &'a [u8], encoding: TextEncoding) -> Result<Self> {
+        Ok(encoding.decode_text(buffer).into_owned())
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
-        unsafe { super::super::environment::DB_ENCODING }
-            .encode(self)
-            .0
-            .len() as ffi::SQLULEN
+        self.len() as ffi::SQLULEN
     }
 
     fn value_ptr(&self) -> ffi::SQLPOINTER {
-        unsafe { super::super::environment::DB_ENCODING }
-            .encode(self)
-            .0
-            .as_ptr() as *const Self as ffi::SQLPOINTER
+        self.as_ptr() as *const Self as ffi::SQLPOINTER
     }
 
     fn null_bytes_count() -> usize {
         1
     }
 
-    fn encoded_value(&self) -> EncodedValue {
-        EncodedValue::new(Some(
-            unsafe { super::super::environment::DB_ENCODING }
-                .encode(self)
-                .0
-                .to_vec(),
-        ))
+    fn null_bytes_count_for(encoding: TextEncoding) -> usize {
+        text_null_bytes_count(encoding)
+    }
+
+    fn encoded_value(&self, encoding: TextEncoding) -> EncodedValue {
+        EncodedValue::new(Some(encoding.encode_text(self).into_owned()))
     }
 }
 
@@ -237,44 +242,37 @@ unsafe impl<'a> OdbcType<'a> for &'a str {
     fn c_data_type() -> ffi::SqlCDataType {
         ffi::SQL_C_CHAR
     }
+    fn c_data_type_for(encoding: TextEncoding) -> ffi::SqlCDataType {
+        text_c_data_type(encoding)
+    }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        let cow = unsafe { super::super::environment::DB_ENCODING }
-            .decode(buffer)
-            .0;
-        match cow {
-            Borrowed(strref) => strref,
-            Owned(_string) => {
-                panic!("Couldn't convert data to `&str`. Try `String` or `Cow<str>` instead.")
-            }
+    fn convert(buffer: &'a [u8], encoding: TextEncoding) -> Result<Self> {
+        match encoding.decode_text(buffer) {
+            Borrowed(strref) => Ok(strref),
+            Owned(_string) => Err(Error::Conversion(
+                "Couldn't convert data to `&str`. Try `String` or `Cow<str>` instead.".to_owned(),
+            )),
         }
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
-        unsafe { super::super::environment::DB_ENCODING }
-            .encode(self)
-            .0
-            .len() as ffi::SQLULEN
+        self.len() as ffi::SQLULEN
     }
 
     fn value_ptr(&self) -> ffi::SQLPOINTER {
-        unsafe { super::super::environment::DB_ENCODING }
-            .encode(self)
-            .0
-            .as_ptr() as *const Self as ffi::SQLPOINTER
+        self.as_ptr() as *const Self as ffi::SQLPOINTER
     }
 
     fn null_bytes_count() -> usize {
         1
     }
 
-    fn encoded_value(&self) -> EncodedValue {
-        EncodedValue::new(Some(
-            unsafe { super::super::environment::DB_ENCODING }
-                .encode(self)
-                .0
-                .to_vec(),
-        ))
+    fn null_bytes_count_for(encoding: TextEncoding) -> usize {
+        text_null_bytes_count(encoding)
+    }
+
+    fn encoded_value(&self, encoding: TextEncoding) -> EncodedValue {
+        EncodedValue::new(Some(encoding.encode_text(self).into_owned()))
     }
 }
 
@@ -285,44 +283,72 @@ unsafe impl<'a> OdbcType<'a> for ::std::borrow::Cow<'a, str> {
     fn c_data_type() -> ffi::SqlCDataType {
         ffi::SQL_C_CHAR
     }
+    fn c_data_type_for(encoding: TextEncoding) -> ffi::SqlCDataType {
+        text_c_data_type(encoding)
+    }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        unsafe { super::super::environment::DB_ENCODING.decode(buffer).0 }
+    fn convert(buffer: &'a [u8], encoding: TextEncoding) -> Result<Self> {
+        Ok(encoding.decode_text(buffer))
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
-        unsafe { super::super::environment::DB_ENCODING }
-            .encode(self)
-            .0
-            .len() as ffi::SQLULEN
+        self.len() as ffi::SQLULEN
     }
 
     fn value_ptr(&self) -> ffi::SQLPOINTER {
-        unsafe { super::super::environment::DB_ENCODING }
-            .encode(self)
-            .0
-            .as_ptr() as *const Self as ffi::SQLPOINTER
+        self.as_ptr() as *const Self as ffi::SQLPOINTER
     }
 
     fn null_bytes_count() -> usize {
         1
     }
 
-    fn encoded_value(&self) -> EncodedValue {
-        EncodedValue::new(Some(
-            unsafe { super::super::environment::DB_ENCODING }
-                .encode(self)
-                .0
-                .to_vec(),
-        ))
+    fn null_bytes_count_for(encoding: TextEncoding) -> usize {
+        text_null_bytes_count(encoding)
+    }
+
+    fn encoded_value(&self, encoding: TextEncoding) -> EncodedValue {
+        EncodedValue::new(Some(encoding.encode_text(self).into_owned()))
+    }
+}
+
+/// Text is exchanged as UTF-16 with the wide functions
+fn text_c_data_type(encoding: TextEncoding) -> ffi::SqlCDataType {
+    if encoding.is_wide() {
+        ffi::SQL_C_WCHAR
+    } else {
+        ffi::SQL_C_CHAR
     }
 }
 
-fn convert_primitive<T>(buf: &[u8]) -> T
+fn text_null_bytes_count(encoding: TextEncoding) -> usize {
+    if encoding.is_wide() {
+        2
+    } else {
+        1
+    }
+}
+
+/// Fails unless `buffer` holds exactly one value of the fixed size type `T`
+fn check_size<T>(buffer: &[u8]) -> Result<()> {
+    if buffer.len() == size_of::<T>() {
+        Ok(())
+    } else {
+        Err(Error::Conversion(format!(
+            "Expected {} bytes for `{}`, got {}",
+            size_of::<T>(),
+            type_name::<T>(),
+            buffer.len()
+        )))
+    }
+}
+
+fn convert_primitive<T>(buf: &[u8]) -> Result<T>
 where
     T: Copy,
 {
-    unsafe { from_raw_parts(buf.as_ptr() as *const T, 1)[0] }
+    check_size::<T>(buf)?;
+    Ok(unsafe { (buf.as_ptr() as *const T).read_unaligned() })
 }
 
 unsafe impl<'a> OdbcType<'a> for u8 {
@@ -333,7 +359,7 @@ unsafe impl<'a> OdbcType<'a> for u8 {
         ffi::SQL_C_UTINYINT
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
         convert_primitive(buffer)
     }
 
@@ -344,7 +370,7 @@ unsafe impl<'a> OdbcType<'a> for u8 {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -357,7 +383,7 @@ unsafe impl<'a> OdbcType<'a> for i8 {
         ffi::SQL_C_STINYINT
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
         convert_primitive(buffer)
     }
 
@@ -368,7 +394,7 @@ unsafe impl<'a> OdbcType<'a> for i8 {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -381,7 +407,7 @@ unsafe impl<'a> OdbcType<'a> for i16 {
         ffi::SQL_C_SSHORT
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
         convert_primitive(buffer)
     }
 
@@ -392,7 +418,7 @@ unsafe impl<'a> OdbcType<'a> for i16 {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -405,7 +431,7 @@ unsafe impl<'a> OdbcType<'a> for u16 {
         ffi::SQL_C_USHORT
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
         convert_primitive(buffer)
     }
 
@@ -416,7 +442,7 @@ unsafe impl<'a> OdbcType<'a> for u16 {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -429,7 +455,7 @@ unsafe impl<'a> OdbcType<'a> for i32 {
         ffi::SQL_C_SLONG
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
         convert_primitive(buffer)
     }
 
@@ -440,7 +466,7 @@ unsafe impl<'a> OdbcType<'a> for i32 {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -453,7 +479,7 @@ unsafe impl<'a> OdbcType<'a> for u32 {
         ffi::SQL_C_ULONG
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
         convert_primitive(buffer)
     }
 
@@ -464,7 +490,7 @@ unsafe impl<'a> OdbcType<'a> for u32 {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -477,7 +503,7 @@ unsafe impl<'a> OdbcType<'a> for i64 {
         ffi::SQL_C_SBIGINT
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
         convert_primitive(buffer)
     }
 
@@ -488,7 +514,7 @@ unsafe impl<'a> OdbcType<'a> for i64 {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -501,7 +527,7 @@ unsafe impl<'a> OdbcType<'a> for u64 {
         ffi::SQL_C_UBIGINT
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
         convert_primitive(buffer)
     }
 
@@ -512,7 +538,7 @@ unsafe impl<'a> OdbcType<'a> for u64 {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -525,7 +551,7 @@ unsafe impl<'a> OdbcType<'a> for f32 {
         ffi::SQL_C_FLOAT
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
         convert_primitive(buffer)
     }
 
@@ -536,7 +562,7 @@ unsafe impl<'a> OdbcType<'a> for f32 {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -549,7 +575,7 @@ unsafe impl<'a> OdbcType<'a> for f64 {
         ffi::SQL_C_DOUBLE
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
         convert_primitive(buffer)
     }
 
@@ -560,7 +586,7 @@ unsafe impl<'a> OdbcType<'a> for f64 {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -573,9 +599,9 @@ unsafe impl<'a> OdbcType<'a> for bool {
         ffi::SQL_C_BIT
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        assert!(buffer.len() == 1);
-        buffer[0] > 0
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
+        check_size::<Self>(buffer)?;
+        Ok(buffer[0] > 0)
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
@@ -585,7 +611,7 @@ unsafe impl<'a> OdbcType<'a> for bool {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -600,12 +626,8 @@ unsafe impl<'a> OdbcType<'a> for SqlDate {
         ffi::SQL_C_DATE
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        assert_eq!(buffer.len(), size_of::<Self>());
-        unsafe {
-            let ptr = buffer.as_ptr() as *const [u8; size_of::<Self>()];
-            transmute(*ptr)
-        }
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
+        convert_primitive(buffer)
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
@@ -615,7 +637,7 @@ unsafe impl<'a> OdbcType<'a> for SqlDate {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -630,12 +652,8 @@ unsafe impl<'a> OdbcType<'a> for SqlTime {
         ffi::SQL_C_TIME
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        assert_eq!(buffer.len(), size_of::<Self>());
-        unsafe {
-            let ptr = buffer.as_ptr() as *const [u8; size_of::<Self>()];
-            transmute(*ptr)
-        }
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
+        convert_primitive(buffer)
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
@@ -645,7 +663,7 @@ unsafe impl<'a> OdbcType<'a> for SqlTime {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -660,12 +678,8 @@ unsafe impl<'a> OdbcType<'a> for SqlTimestamp {
         ffi::SQL_C_TYPE_TIMESTAMP
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        assert_eq!(buffer.len(), size_of::<Self>());
-        unsafe {
-            let ptr = buffer.as_ptr() as *const [u8; size_of::<Self>()];
-            transmute(*ptr)
-        }
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
+        convert_primitive(buffer)
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
@@ -675,7 +689,7 @@ unsafe impl<'a> OdbcType<'a> for SqlTimestamp {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -691,12 +705,8 @@ unsafe impl<'a> OdbcType<'a> for SqlSsTime2 {
         ffi::SQL_C_BINARY
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        assert_eq!(buffer.len(), size_of::<Self>());
-        unsafe {
-            let ptr = buffer.as_ptr() as *const [u8; size_of::<Self>()];
-            transmute(*ptr)
-        }
+    fn convert(buffer: &'a [u8], _: TextEncoding) -> Result<Self> {
+        convert_primitive(buffer)
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
@@ -706,7 +716,7 @@ unsafe impl<'a> OdbcType<'a> for SqlSsTime2 {
         self as *const Self as ffi::SQLPOINTER
     }
 
-    fn encoded_value(&self) -> EncodedValue {
+    fn encoded_value(&self, _: TextEncoding) -> EncodedValue {
         EncodedValue::new(None)
     }
 }
@@ -723,8 +733,12 @@ where
         T::c_data_type()
     }
 
-    fn convert(buffer: &'a [u8]) -> Self {
-        Some(T::convert(buffer))
+    fn c_data_type_for(encoding: TextEncoding) -> ffi::SqlCDataType {
+        T::c_data_type_for(encoding)
+    }
+
+    fn convert(buffer: &'a [u8], encoding: TextEncoding) -> Result<Self> {
+        T::convert(buffer, encoding).map(Some)
     }
 
     fn column_size(&self) -> ffi::SQLULEN {
@@ -746,8 +760,15 @@ where
         T::null_bytes_count()
     }
 
-    fn encoded_value(&self) -> EncodedValue {
-        EncodedValue::new(None)
+    fn null_bytes_count_for(encoding: TextEncoding) -> usize {
+        T::null_bytes_count_for(encoding)
+    }
+
+    fn encoded_value(&self, encoding: TextEncoding) -> EncodedValue {
+        match self {
+            Some(t) => t.encoded_value(encoding),
+            None => EncodedValue::new(None),
+        }
     }
 }
 
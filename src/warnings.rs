@@ -0,0 +1,168 @@
+//! Warnings of calls which returned `SQL_SUCCESS_WITH_INFO`
+use super::error::record_sqlcode;
+use super::{DiagnosticRecord, Error, Result};
+use std::cell::RefCell;
+
+/// Number of warnings kept per statement or connection. Older ones are discarded.
+const MAX_WARNINGS: usize = 64;
+
+/// Decides which warnings are promoted to errors
+///
+/// Warnings which are not promoted are logged and kept on the statement or connection, see
+/// `Statement::warnings`. A promoted warning fails the call with an `Error` whose first record is
+/// the promoted warning. Note that the CLI function itself has succeeded in this case, e.g. the
+/// statement has been executed.
+///
+/// ```
+/// # use ibm_db::WarningPolicy;
+/// // Fail on truncated data and on `SQL0445W` (value truncated by a function)
+/// let policy = WarningPolicy::new()
+///     .promote_sqlstate("01004")
+///     .promote_sqlcode(445);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct WarningPolicy {
+    all: bool,
+    sqlstates: Vec<String>,
+    sqlcodes: Vec<i32>,
+}
+
+impl WarningPolicy {
+    /// Keeps all warnings, none is promoted to an error
+    pub fn new() -> WarningPolicy {
+        WarningPolicy::default()
+    }
+
+    /// Promotes every warning to an error
+    pub fn promote_all(mut self) -> WarningPolicy {
+        self.all = true;
+        self
+    }
+
+    /// Promotes warnings with the given SQLSTATE, e.g. `01004` for truncated strings, or the
+    /// given SQLSTATE class, e.g. `01` for all standard warnings
+    pub fn promote_sqlstate<S: Into<String>>(mut self, sqlstate: S) -> WarningPolicy {
+        self.sqlstates.push(sqlstate.into());
+        self
+    }
+
+    /// Promotes warnings with the given DB2 SQLCODE, e.g. `100` for `SQL0100W`
+    pub fn promote_sqlcode(mut self, sqlcode: i32) -> WarningPolicy {
+        self.sqlcodes.push(sqlcode);
+        self
+    }
+
+    /// `true` if `record` is promoted to an error
+    pub fn promotes(&self, record: &DiagnosticRecord) -> bool {
+        if self.all {
+            return true;
+        }
+        let state = &record.get_raw_state()[..5];
+        if self
+            .sqlstates
+            .iter()
+            .any(|prefix| state.starts_with(prefix.as_bytes()))
+        {
+            return true;
+        }
+        match record_sqlcode(record) {
+            Some(code) => self.sqlcodes.contains(&code),
+            None => false,
+        }
+    }
+}
+
+/// Warnings kept by a statement or connection
+#[derive(Debug, Default)]
+pub(crate) struct Warnings {
+    records: RefCell<Vec<DiagnosticRecord>>,
+    policy: WarningPolicy,
+}
+
+impl Warnings {
+    pub(crate) fn with_policy(policy: WarningPolicy) -> Warnings {
+        Warnings {
+            records: RefCell::new(Vec::new()),
+            policy,
+        }
+    }
+
+    pub(crate) fn policy(&self) -> &WarningPolicy {
+        &self.policy
+    }
+
+    pub(crate) fn set_policy(&mut self, policy: WarningPolicy) {
+        self.policy = policy;
+    }
+
+    pub(crate) fn get(&self) -> Vec<DiagnosticRecord> {
+        self.records.borrow().clone()
+    }
+
+    pub(crate) fn take(&self) -> Vec<DiagnosticRecord> {
+        self.records.replace(Vec::new())
+    }
+
+    /// Keeps the warnings of a call, or fails with them if the policy promotes one
+    pub(crate) fn keep(&self, mut records: Vec<DiagnosticRecord>) -> Result<()> {
+        if let Some(pos) = records.iter().position(|r| self.policy.promotes(r)) {
+            let promoted = records.remove(pos);
+            records.insert(0, promoted);
+            return Err(Error::from_records(records));
+        }
+        let mut kept = self.records.borrow_mut();
+        kept.extend(records);
+        let excess = kept.len().saturating_sub(MAX_WARNINGS);
+        kept.drain(..excess);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn promotion() {
+        let record = DiagnosticRecord::still_executing();
+        assert!(!WarningPolicy::new().promotes(&record));
+        assert!(WarningPolicy::new().promote_all().promotes(&record));
+        assert!(WarningPolicy::new()
+            .promote_sqlstate("HY010")
+            .promotes(&record));
+        assert!(WarningPolicy::new()
+            .promote_sqlstate("HY")
+            .promotes(&record));
+        assert!(!WarningPolicy::new()
+            .promote_sqlstate("01004")
+            .promotes(&record));
+        assert!(WarningPolicy::new().promote_sqlcode(-1).promotes(&record));
+    }
+
+    #[test]
+    fn keeps_most_recent() {
+        let warnings = Warnings::default();
+        for _ in 0..MAX_WARNINGS {
+            warnings.keep(vec![DiagnosticRecord::empty()]).unwrap();
+        }
+        warnings
+            .keep(vec![DiagnosticRecord::still_executing()])
+            .unwrap();
+        let kept = warnings.take();
+        assert_eq!(kept.len(), MAX_WARNINGS);
+        assert_eq!(&kept[MAX_WARNINGS - 1].get_raw_state()[..5], b"HY010");
+        assert!(warnings.get().is_empty());
+    }
+
+    #[test]
+    fn promoted_record_comes_first() {
+        let warnings = Warnings::with_policy(WarningPolicy::new().promote_sqlstate("HY010"));
+        let records = vec![
+            DiagnosticRecord::empty(),
+            DiagnosticRecord::still_executing(),
+        ];
+        let error = warnings.keep(records).unwrap_err();
+        assert_eq!(error.sqlstate().unwrap(), "HY010");
+        assert!(warnings.get().is_empty());
+    }
+}